@@ -0,0 +1,30 @@
+//! Scalar versus SIMD audio bit extraction on a 1M-sample buffer
+//!
+//! Run with `cargo bench --features simd --bench extract_low_bits`.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use quantum_rng::audio::{extract_low_bits, extract_low_bits_scalar};
+
+const SAMPLES: usize = 1_000_000;
+
+fn bench_extract_low_bits(c: &mut Criterion) {
+    // Deterministic noise-like samples spanning the 16-bit range
+    let samples: Vec<i32> = (0..SAMPLES as u32)
+        .map(|i| (i.wrapping_mul(2_654_435_761) >> 16) as i32 - 32_768)
+        .collect();
+
+    let mut group = c.benchmark_group("extract_low_bits");
+    group.throughput(Throughput::Elements(SAMPLES as u64));
+    for bits_per_sample in [1u8, 4, 16] {
+        group.bench_with_input(BenchmarkId::new("scalar", bits_per_sample), &bits_per_sample, |b, &bits| {
+            b.iter(|| extract_low_bits_scalar(black_box(&samples), bits))
+        });
+        group.bench_with_input(BenchmarkId::new("simd", bits_per_sample), &bits_per_sample, |b, &bits| {
+            b.iter(|| extract_low_bits(black_box(&samples), bits))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_extract_low_bits);
+criterion_main!(benches);
@@ -1,103 +1,500 @@
-use anyhow::{Result, anyhow};
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::thread;
 
+use crate::error::{QrngError, Result};
+use crate::progress;
+use crate::{QrngSource, UnitsRead};
+
+/// Interval between checks of the shared sample buffer
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Extra recording time allowed beyond the expected duration before giving up
+const RECORDING_TIMEOUT_MARGIN_SECS: f64 = 10.0;
+
+/// Time without a single sample after which `read_chunk` gives up on the stream
+const SILENT_STREAM_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Length of the recording checked for a muted microphone before collection starts
+const PREROLL_DURATION: Duration = Duration::from_millis(250);
+
+/// Pre-roll RMS, as a fraction of full scale, below which the microphone is
+/// probably muted; one 16-bit LSB, since live electronics always toggle the LSB
+const MIN_PREROLL_RMS: f64 = 1.0 / 32768.0;
+
+/// Sample rate assumed by `estimated_rate_bits_per_sec` before the stream has started
+const ASSUMED_SAMPLE_RATE: u32 = 48_000;
+
+/// Number of low bits harvested per sample by default
+pub const DEFAULT_AUDIO_BITS: u8 = 1;
+
+/// Bits per sample above which speech or music may leak into the output
+pub const MAX_RECOMMENDED_AUDIO_BITS: u8 = 4;
+
+/// Extraction settings for a microphone source
+#[derive(Debug, Clone)]
+pub struct AudioConfig {
+    /// Number of low bits harvested from each sample (1-16)
+    pub bits_per_sample: u8,
+    /// Keep only every `decimation`-th sample (1 keeps all)
+    ///
+    /// Skipping samples breaks up periodic interference such as mains hum,
+    /// at the cost of recording `decimation` times longer.
+    pub decimation: usize,
+    /// Input device to record from, matched by name (see `list_input_devices`);
+    /// `None` uses the system default
+    pub device: Option<String>,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        AudioConfig {
+            bits_per_sample: DEFAULT_AUDIO_BITS,
+            decimation: 1,
+            device: None,
+        }
+    }
+}
+
+/// Microphone electronics quantum noise source
+///
+/// The input stream is started on first use and keeps recording into a shared
+/// buffer until the source is dropped, so consecutive calls see contiguous
+/// audio: samples recorded beyond what a collection needs stay buffered for
+/// the next read.
+pub struct AudioSource {
+    stream: Option<cpal::Stream>,
+    samples: Arc<Mutex<Vec<i32>>>,
+    sample_rate: u32,
+    bits_per_sample: u8,
+    decimation: usize,
+    device: Option<String>,
+    /// Samples to skip before the next kept one, carried across drains
+    decimation_skip: usize,
+    raw_capture: Option<Box<dyn Write>>,
+    /// Samples taken from the stream, before decimation
+    samples_read: usize,
+    /// When `read_chunk` last drained any samples (or the stream started)
+    last_samples: Option<Instant>,
+}
+
+impl Default for AudioSource {
+    fn default() -> Self {
+        Self::with_config(AudioConfig::default())
+    }
+}
+
+impl AudioSource {
+    /// Source harvesting only the least significant bit of each sample
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// # Arguments
+    /// * `bits_per_sample` - Number of low bits harvested from each sample (1-16)
+    pub fn with_bits_per_sample(bits_per_sample: u8) -> Self {
+        Self::with_config(AudioConfig { bits_per_sample, ..AudioConfig::default() })
+    }
+
+    /// # Arguments
+    /// * `config` - Bits per sample and decimation settings
+    pub fn with_config(config: AudioConfig) -> Self {
+        let bits_per_sample = config.bits_per_sample.clamp(1, 16);
+        if bits_per_sample > MAX_RECOMMENDED_AUDIO_BITS {
+            log::warn!(
+                "Harvesting {} bits per sample; bits above {} carry audible signal unless the microphone is silent",
+                bits_per_sample, MAX_RECOMMENDED_AUDIO_BITS
+            );
+        }
+
+        AudioSource {
+            stream: None,
+            samples: Arc::new(Mutex::new(Vec::new())),
+            sample_rate: 0,
+            bits_per_sample,
+            decimation: config.decimation.max(1),
+            device: config.device,
+            decimation_skip: 0,
+            raw_capture: None,
+            samples_read: 0,
+            last_samples: None,
+        }
+    }
+
+    /// Start recording into the shared sample buffer if not already running
+    fn ensure_stream(&mut self) -> Result<()> {
+        if self.stream.is_some() {
+            return Ok(());
+        }
+
+        log::info!("Initializing audio subsystem for quantum noise collection...");
+
+        let host = cpal::default_host();
+        let device = select_input_device(&host, self.device.as_deref())?;
+
+        log::info!("Using input device: {}", device.name()?);
+
+        let config = device.default_input_config()?;
+        log::info!("Sample format: {:?}, Sample rate: {}", config.sample_format(), config.sample_rate().0);
+
+        let sample_format = config.sample_format();
+        let config: cpal::StreamConfig = config.into();
+
+        let samples_clone = self.samples.clone();
+
+        // Setup callback function for audio data. Samples are stored as i32
+        // so 32-bit formats (including 24-bit interfaces, which deliver I32)
+        // keep their genuine low-order noise bits instead of being rescaled
+        log::debug!("Using {:?} sample conversion", sample_format);
+        let stream = match sample_format {
+            cpal::SampleFormat::I16 => device.build_input_stream(
+                &config,
+                move |data: &[i16], _: &_| {
+                    let mut samples = samples_clone.lock().unwrap();
+                    samples.extend(data.iter().map(|&s| s as i32));
+                },
+                |err| log::error!("Error in audio stream: {}", err),
+                None,
+            )?,
+            cpal::SampleFormat::U16 => device.build_input_stream(
+                &config,
+                move |data: &[u16], _: &_| {
+                    let mut samples = samples_clone.lock().unwrap();
+                    samples.extend(data.iter().map(|&s| s as i16 as i32));
+                },
+                |err| log::error!("Error in audio stream: {}", err),
+                None,
+            )?,
+            cpal::SampleFormat::I32 => device.build_input_stream(
+                &config,
+                move |data: &[i32], _: &_| {
+                    let mut samples = samples_clone.lock().unwrap();
+                    samples.extend_from_slice(data);
+                },
+                |err| log::error!("Error in audio stream: {}", err),
+                None,
+            )?,
+            cpal::SampleFormat::U32 => device.build_input_stream(
+                &config,
+                move |data: &[u32], _: &_| {
+                    // Reinterpreting the bits leaves the low-order bits unchanged
+                    let mut samples = samples_clone.lock().unwrap();
+                    samples.extend(data.iter().map(|&s| s as i32));
+                },
+                |err| log::error!("Error in audio stream: {}", err),
+                None,
+            )?,
+            cpal::SampleFormat::F32 => device.build_input_stream(
+                &config,
+                move |data: &[f32], _: &_| {
+                    let mut samples = samples_clone.lock().unwrap();
+                    samples.extend(data.iter().map(|&s| (s * 32767.0) as i16 as i32));
+                },
+                |err| log::error!("Error in audio stream: {}", err),
+                None,
+            )?,
+            _ => return Err(QrngError::UnsupportedFormat(format!("audio sample format {:?}", sample_format))),
+        };
+
+        log::info!("Recording quantum noise from microphone... (keep room silent)");
+        log::info!("For best results: Shield microphone from external noise");
+
+        // Start recording
+        stream.play()?;
+
+        self.sample_rate = config.sample_rate.0;
+        self.stream = Some(stream);
+        self.last_samples = Some(Instant::now());
+
+        // Samples are stored at their native width except F32, which is scaled to 16 bits
+        let full_scale = match sample_format {
+            cpal::SampleFormat::I32 | cpal::SampleFormat::U32 => 2f64.powi(31),
+            _ => 32768.0,
+        };
+        self.check_input_level(full_scale);
+        Ok(())
+    }
+
+    /// Record a short pre-roll and warn if it is too quiet to contain any noise
+    ///
+    /// The pre-roll samples are discarded so collection starts after the check.
+    fn check_input_level(&self, full_scale: f64) {
+        thread::sleep(PREROLL_DURATION);
+        let preroll = self.drain_samples();
+        if preroll.is_empty() {
+            log::warn!("No audio received in the first {:?}. The microphone may be disconnected", PREROLL_DURATION);
+            return;
+        }
+
+        let rms = rms_amplitude(&preroll) / full_scale;
+        if rms < MIN_PREROLL_RMS {
+            log::warn!(
+                "Input level is very low (RMS {:.1} dBFS). The microphone may be muted; raise the input gain before recording",
+                20.0 * rms.max(f64::MIN_POSITIVE).log10()
+            );
+        }
+    }
+
+    /// Take every sample recorded so far out of the shared buffer
+    fn drain_samples(&self) -> Vec<i32> {
+        std::mem::take(&mut *self.samples.lock().unwrap())
+    }
+
+    /// Count samples and write them to the raw capture, if one is set
+    fn capture(&mut self, samples: &[i32]) -> Result<()> {
+        self.samples_read += samples.len();
+        if let Some(capture) = self.raw_capture.as_mut() {
+            let bytes: Vec<u8> = samples.iter().flat_map(|sample| sample.to_le_bytes()).collect();
+            capture.write_all(&bytes)?;
+        }
+        Ok(())
+    }
+
+    /// Keep every `decimation`-th sample, continuing the pattern from the previous call
+    fn decimate(&mut self, samples: Vec<i32>) -> Vec<i32> {
+        if self.decimation == 1 {
+            return samples;
+        }
+
+        let kept: Vec<i32> = samples.iter().skip(self.decimation_skip).step_by(self.decimation).copied().collect();
+        let consumed = self.decimation_skip + kept.len() * self.decimation;
+        self.decimation_skip = consumed.saturating_sub(samples.len());
+        kept
+    }
+}
+
+/// Find the input device named `name`, or the default input
+///
+/// An exact name wins over a case-insensitive substring match. A name that
+/// matches nothing falls back to the default device with a warning.
+fn select_input_device(host: &cpal::Host, name: Option<&str>) -> Result<cpal::Device> {
+    if let Some(wanted) = name {
+        let devices: Vec<cpal::Device> = host.input_devices()?.collect();
+        let names: Vec<Option<String>> = devices.iter().map(|device| device.name().ok()).collect();
+        let lowercase = wanted.to_lowercase();
+        let exact = names.iter().position(|name| name.as_deref() == Some(wanted));
+        let partial = || names.iter().position(|name| name.as_ref().is_some_and(|name| name.to_lowercase().contains(&lowercase)));
+
+        if let Some(index) = exact.or_else(partial) {
+            return Ok(devices.into_iter().nth(index).expect("index into the same device list"));
+        }
+        log::warn!("No audio input matches '{wanted}' (see `list-devices`); using the default input");
+    }
+
+    host.default_input_device()
+        .ok_or_else(|| QrngError::NoDevice("No microphone available. Check connections and permissions".to_string()))
+}
+
+/// Root-mean-square deviation of the samples from their mean, in sample units
+///
+/// The mean is removed so a DC offset does not hide a constant (muted) input.
+fn rms_amplitude(samples: &[i32]) -> f64 {
+    let n = samples.len() as f64;
+    let mean = samples.iter().map(|&s| s as f64).sum::<f64>() / n;
+    let variance = samples.iter().map(|&s| (s as f64 - mean).powi(2)).sum::<f64>() / n;
+    variance.sqrt()
+}
+
+/// Extract the low `bits_per_sample` bits of each sample, least significant first
+#[cfg(not(feature = "simd"))]
+pub fn extract_low_bits(samples: &[i32], bits_per_sample: u8) -> Vec<u8> {
+    extract_low_bits_scalar(samples, bits_per_sample)
+}
+
+/// Extract the low `bits_per_sample` bits of each sample, least significant first
+///
+/// Shifts and masks eight samples at a time; the output is identical to the
+/// scalar version.
+#[cfg(feature = "simd")]
+pub fn extract_low_bits(samples: &[i32], bits_per_sample: u8) -> Vec<u8> {
+    use wide::i32x8;
+
+    const LANES: usize = 8;
+    let per_sample = bits_per_sample as usize;
+    let mut bits = vec![0u8; samples.len() * per_sample];
+    let one = i32x8::splat(1);
+
+    let blocks = samples.chunks_exact(LANES);
+    let rest = blocks.remainder();
+    for (block, out) in blocks.zip(bits.chunks_exact_mut(LANES * per_sample)) {
+        let lanes: [i32; LANES] = block.try_into().unwrap();
+        let lanes = i32x8::from(lanes);
+        for bit in 0..per_sample {
+            let plane = ((lanes >> bit as u32) & one).to_array();
+            for (lane, &value) in plane.iter().enumerate() {
+                out[lane * per_sample + bit] = value as u8;
+            }
+        }
+    }
+
+    let tail = (samples.len() - rest.len()) * per_sample;
+    bits[tail..].copy_from_slice(&extract_low_bits_scalar(rest, bits_per_sample));
+    bits
+}
+
+/// Scalar reference version of `extract_low_bits`, one sample and bit at a time
+pub fn extract_low_bits_scalar(samples: &[i32], bits_per_sample: u8) -> Vec<u8> {
+    let mut bits = Vec::with_capacity(samples.len() * bits_per_sample as usize);
+    for &sample in samples {
+        for bit in 0..bits_per_sample {
+            bits.push(((sample >> bit) & 1) as u8);
+        }
+    }
+    bits
+}
+
+impl QrngSource for AudioSource {
+    /// Wait briefly for the stream to fill, then extract the low bits of each sample
+    ///
+    /// Fails once the stream has delivered nothing for `SILENT_STREAM_TIMEOUT`,
+    /// so a dead microphone cannot stall an adapter-driven collection forever.
+    fn read_chunk(&mut self) -> Result<Vec<u8>> {
+        self.ensure_stream()?;
+        thread::sleep(POLL_INTERVAL);
+
+        let samples = self.drain_samples();
+        let now = Instant::now();
+        if !samples.is_empty() {
+            self.last_samples = Some(now);
+        } else if let Some(silent) = self.last_samples.map(|last| now - last).filter(|&silent| silent > SILENT_STREAM_TIMEOUT) {
+            return Err(QrngError::Audio(format!("No audio for {:.1?}. Check that the microphone is delivering samples", silent)));
+        }
+        self.capture(&samples)?;
+        let samples = self.decimate(samples);
+        Ok(extract_low_bits(&samples, self.bits_per_sample))
+    }
+
+    /// Assumes one mono channel at 48 kHz unless the stream is already running
+    fn estimated_rate_bits_per_sec(&self) -> Option<f64> {
+        let sample_rate = if self.sample_rate > 0 { self.sample_rate } else { ASSUMED_SAMPLE_RATE };
+        Some(sample_rate as f64 * self.bits_per_sample as f64 / self.decimation as f64)
+    }
+
+    /// Captures every sample before decimation as a little-endian i32, channels
+    /// interleaved; 16-bit and float formats use the i16 range
+    fn set_raw_capture(&mut self, sink: Box<dyn Write>) -> bool {
+        self.raw_capture = Some(sink);
+        true
+    }
+
+    /// Samples before decimation, so --decimate shows up as a lower yield
+    fn units_read(&self) -> Option<UnitsRead> {
+        Some(UnitsRead { count: self.samples_read, unit: "sample" })
+    }
+
+    fn collect_bits_cancellable(&mut self, num_bits: usize, cancel: &AtomicBool) -> Result<Vec<u8>> {
+        self.ensure_stream()?;
+
+        // Record until enough samples have arrived, giving up after a
+        // generous multiple of the expected time
+        let samples_needed = num_bits.div_ceil(self.bits_per_sample as usize) * self.decimation;
+        let expected_secs = samples_needed as f64 / self.sample_rate.max(1) as f64;
+        let timeout = Duration::from_secs_f64(expected_secs * 3.0 + RECORDING_TIMEOUT_MARGIN_SECS);
+        let start = Instant::now();
+        let bar = progress::progress_bar(samples_needed, "samples");
+
+        loop {
+            let collected = self.samples.lock().unwrap().len();
+            if collected >= samples_needed {
+                break;
+            }
+
+            if cancel.load(Ordering::Relaxed) {
+                bar.abandon();
+                log::warn!("Recording interrupted after {}/{} samples", collected, samples_needed);
+                break;
+            }
+
+            if start.elapsed() > timeout {
+                bar.abandon();
+                log::warn!("No more audio after {:.1?}. Check that the microphone is delivering samples", start.elapsed());
+                return Err(QrngError::InsufficientData { got: collected, requested: samples_needed });
+            }
+
+            bar.set_position(collected as u64);
+            thread::sleep(POLL_INTERVAL);
+        }
+        bar.finish_and_clear();
+
+        // Extract bits from samples; any recorded past the request go back
+        // to the front of the buffer for the next call
+        let mut samples = self.drain_samples();
+        let mut surplus = samples.split_off(samples_needed.min(samples.len()));
+        if !surplus.is_empty() {
+            let mut buffer = self.samples.lock().unwrap();
+            surplus.append(&mut buffer);
+            *buffer = surplus;
+        }
+
+        log::info!("Collected {} audio samples, extracting quantum noise bits...", samples.len());
+
+        self.capture(&samples)?;
+        let samples = self.decimate(samples);
+        if self.decimation > 1 {
+            log::info!("Kept {} samples after decimating by {}", samples.len(), self.decimation);
+        }
+
+        // Extract the low bits
+        let mut bits = extract_low_bits(&samples, self.bits_per_sample);
+        bits.truncate(num_bits);
+
+        log::info!("Quantum noise extraction complete");
+        Ok(bits)
+    }
+}
+
+/// Names of the audio input devices on the default host
+///
+/// # Returns
+/// Device names, with the default input device first
+pub fn list_input_devices() -> Result<Vec<String>> {
+    let host = cpal::default_host();
+    let default_name = host.default_input_device().and_then(|device| device.name().ok());
+
+    let mut names: Vec<String> = host.input_devices()?
+        .filter_map(|device| device.name().ok())
+        .collect();
+    if let Some(default_name) = default_name {
+        if let Some(position) = names.iter().position(|name| *name == default_name) {
+            let name = names.remove(position);
+            names.insert(0, name);
+        }
+    }
+
+    Ok(names)
+}
+
 /// Generates random bits using quantum noise from microphone electronics
 ///
 /// # Arguments
 /// * `num_bits` - Number of random bits to generate
+/// * `cancel` - Flag that stops collection early when set
 ///
 /// # Returns
-/// Vector of random bits (0s and 1s as u8)
-pub fn audio_qrng(num_bits: usize) -> Result<Vec<u8>> {
-    println!("Initializing audio subsystem for quantum noise collection...");
-    
-    let host = cpal::default_host();
-    let device = host.default_input_device()
-        .ok_or_else(|| anyhow!("No microphone available. Check connections and permissions"))?;
-    
-    println!("Using input device: {}", device.name()?);
-    
-    let config = device.default_input_config()?;
-    println!("Sample format: {:?}, Sample rate: {}", config.sample_format(), config.sample_rate().0);
-    
-    let sample_format = config.sample_format();
-    let config = config.into();
-    
-    let samples = Arc::new(Mutex::new(Vec::new()));
-    let samples_clone = samples.clone();
-    
-    // Determine duration based on needed bits (with safety margin)
-    // Assume 16-bit samples and we extract 1 bit per sample
-    let duration_sec = (num_bits as f32 / config.sample_rate().0 as f32 * 1.5).max(1.0);
-    
-    // Setup callback function for audio data
-    let stream = match sample_format {
-        cpal::SampleFormat::I16 => device.build_input_stream(
-            &config,
-            move |data: &[i16], _: &_| {
-                let mut samples = samples_clone.lock().unwrap();
-                samples.extend_from_slice(data);
-            },
-            |err| eprintln!("Error in audio stream: {}", err),
-            None,
-        )?,
-        cpal::SampleFormat::U16 => device.build_input_stream(
-            &config,
-            move |data: &[u16], _: &_| {
-                let mut samples = samples_clone.lock().unwrap();
-                samples.extend(data.iter().map(|&s| s as i16));
-            },
-            |err| eprintln!("Error in audio stream: {}", err),
-            None,
-        )?,
-        cpal::SampleFormat::F32 => device.build_input_stream(
-            &config,
-            move |data: &[f32], _: &_| {
-                let mut samples = samples_clone.lock().unwrap();
-                samples.extend(data.iter().map(|&s| (s * 32767.0) as i16));
-            },
-            |err| eprintln!("Error in audio stream: {}", err),
-            None,
-        )?,
-        _ => return Err(anyhow!("Unsupported sample format: {:?}", sample_format)),
-    };
-    
-    println!("Recording quantum noise from microphone... (keep room silent)");
-    println!("For best results: Shield microphone from external noise");
-    
-    // Start recording
-    stream.play()?;
-    
-    for i in (1..=duration_sec as u32).rev() {
-        println!("Recording: {} seconds remaining...", i);
-        thread::sleep(Duration::from_secs(1));
-    }
-    
-    // Stop recording
-    drop(stream);
-    
-    // Extract bits from samples
-    let samples = samples.lock().unwrap();
-    let mut bits = Vec::with_capacity(num_bits);
-    
-    println!("Collected {} audio samples, extracting quantum noise bits...", samples.len());
-    
-    for &sample in &*samples {
-        // Extract least significant bit
-        bits.push((sample & 1) as u8);
-        if bits.len() >= num_bits {
-            break;
-        }
-    }
-    
-    if bits.len() < num_bits {
-        println!("Warning: Could only collect {} bits, requested {}", bits.len(), num_bits);
-        return Err(anyhow!("Insufficient audio data collected. Try increasing recording time"));
-    }
-    
-    println!("Quantum noise extraction complete");
-    Ok(bits[..num_bits].to_vec())
-}
\ No newline at end of file
+/// Vector of random bits (0s and 1s as u8), shorter than `num_bits` if cancelled
+pub fn audio_qrng(num_bits: usize, cancel: &AtomicBool) -> Result<Vec<u8>> {
+    AudioSource::new().collect_bits_cancellable(num_bits, cancel)
+}
+
+#[cfg(all(test, feature = "simd"))]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        /// Lengths up to 100 cover empty input, whole lanes and every partial tail
+        #[test]
+        fn simd_matches_scalar(samples in proptest::collection::vec(any::<i32>(), 0..100)) {
+            for bits_per_sample in 1..=16 {
+                prop_assert_eq!(
+                    extract_low_bits(&samples, bits_per_sample),
+                    extract_low_bits_scalar(&samples, bits_per_sample),
+                    "bits_per_sample = {}", bits_per_sample
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,140 @@
+//! Resumable collection state for long runs
+//!
+//! A multi-hour collection from a slow source loses everything if the
+//! process dies before the target is reached. A checkpoint file holds the raw
+//! (unprocessed) bits gathered so far plus enough context to check that a
+//! resumed run continues the same collection. Post-processing only ever
+//! happens on the complete set of bits, so a resumed run produces the same
+//! kind of output as an uninterrupted one.
+//!
+//! # Layout
+//!
+//! All integers are little-endian.
+//!
+//! | Offset | Size | Field |
+//! |--------|------|-------|
+//! | 0      | 4    | Magic `QRCP` (`51 52 43 50`) |
+//! | 4      | 1    | Format version, currently 1 |
+//! | 5      | 1    | Reserved, zero |
+//! | 6      | 2    | Source description length `N` in bytes |
+//! | 8      | 8    | Target number of raw bits |
+//! | 16     | 8    | Number of raw bits collected so far |
+//! | 24     | `N`  | Source description, UTF-8 |
+//! | 24 + `N` | rest | Collected bits, packed 8 per byte, least significant bit first |
+
+use std::fs::File;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::{QrngError, Result};
+use crate::utils::{self, BitOrder};
+
+/// First four bytes of a checkpoint file
+pub const MAGIC: [u8; 4] = *b"QRCP";
+
+/// Checkpoint format version written by this crate
+pub const VERSION: u8 = 1;
+
+/// Length of the fixed part of the file, before the source description
+const FIXED_LEN: usize = 24;
+
+/// Raw bits of an unfinished collection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// Description of the source the bits came from, e.g. "serial port /dev/ttyUSB0"
+    pub source: String,
+    /// Number of raw bits the collection is aiming for
+    pub target_bits: u64,
+    /// Raw bits collected so far (0s and 1s as u8)
+    pub bits: Vec<u8>,
+}
+
+impl Checkpoint {
+    /// An empty checkpoint for a new collection
+    pub fn new(source: impl Into<String>, target_bits: u64) -> Self {
+        Checkpoint { source: source.into(), target_bits, bits: Vec::new() }
+    }
+
+    /// Read the checkpoint at `path`
+    ///
+    /// # Returns
+    /// `None` if no file exists there yet, otherwise the saved checkpoint
+    pub fn load(path: impl AsRef<Path>) -> Result<Option<Checkpoint>> {
+        let bytes = match std::fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        Checkpoint::parse(&bytes).map(Some)
+    }
+
+    /// Decode the contents of a checkpoint file
+    pub fn parse(bytes: &[u8]) -> Result<Checkpoint> {
+        if !bytes.starts_with(&MAGIC) {
+            return Err(QrngError::UnsupportedFormat("not a checkpoint file (bad magic)".to_string()));
+        }
+        if bytes.len() < FIXED_LEN {
+            return Err(QrngError::UnsupportedFormat(format!("checkpoint truncated to {} bytes", bytes.len())));
+        }
+        let version = bytes[4];
+        if version != VERSION {
+            return Err(QrngError::UnsupportedFormat(format!("checkpoint version {version} (this build reads version {VERSION})")));
+        }
+        let source_len = u16::from_le_bytes([bytes[6], bytes[7]]) as usize;
+        let target_bits = u64::from_le_bytes(bytes[8..16].try_into().expect("slice of 8 bytes"));
+        let bit_count = u64::from_le_bytes(bytes[16..24].try_into().expect("slice of 8 bytes"));
+
+        let body_start = FIXED_LEN + source_len;
+        if bytes.len() < body_start {
+            return Err(QrngError::UnsupportedFormat(format!("checkpoint source truncated ({} of {source_len} bytes)", bytes.len() - FIXED_LEN)));
+        }
+        let source = String::from_utf8(bytes[FIXED_LEN..body_start].to_vec())
+            .map_err(|e| QrngError::UnsupportedFormat(format!("checkpoint source: {e}")))?;
+        let body = &bytes[body_start..];
+        if bit_count > body.len() as u64 * 8 {
+            return Err(QrngError::UnsupportedFormat(format!("checkpoint claims {bit_count} bits but holds {}", body.len() * 8)));
+        }
+
+        let mut bits = utils::bytes_to_bits(body, BitOrder::LsbFirst);
+        bits.truncate(bit_count as usize);
+        Ok(Checkpoint { source, target_bits, bits })
+    }
+
+    /// Serialize the checkpoint
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // The length field is 16 bits; a description that long is cut at a character boundary
+        let mut source_len = self.source.len().min(u16::MAX as usize);
+        while !self.source.is_char_boundary(source_len) {
+            source_len -= 1;
+        }
+        let body = utils::bits_to_bytes(&self.bits, BitOrder::LsbFirst);
+
+        let mut bytes = Vec::with_capacity(FIXED_LEN + source_len + body.len());
+        bytes.extend_from_slice(&MAGIC);
+        bytes.push(VERSION);
+        bytes.push(0);
+        bytes.extend_from_slice(&(source_len as u16).to_le_bytes());
+        bytes.extend_from_slice(&self.target_bits.to_le_bytes());
+        bytes.extend_from_slice(&(self.bits.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&self.source.as_bytes()[..source_len]);
+        bytes.extend_from_slice(&body);
+        bytes
+    }
+
+    /// Write the checkpoint to `path`
+    ///
+    /// The file is written next to `path` and renamed over it, so a crash
+    /// while saving leaves the previous checkpoint intact.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let mut temp = PathBuf::from(path);
+        temp.as_mut_os_string().push(".tmp");
+
+        let mut file = File::create(&temp)?;
+        file.write_all(&self.to_bytes())?;
+        file.sync_all()?;
+        drop(file);
+        std::fs::rename(&temp, path)?;
+        Ok(())
+    }
+}
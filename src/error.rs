@@ -0,0 +1,112 @@
+use std::path::PathBuf;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Errors produced by the entropy sources
+#[derive(Debug, Error)]
+pub enum QrngError {
+    /// The requested device is missing, busy or could not be opened
+    #[error("{0}")]
+    NoDevice(String),
+
+    /// The device delivers data in a format the source cannot extract bits from
+    #[error("Unsupported format: {0}")]
+    UnsupportedFormat(String),
+
+    /// The device stopped delivering data before enough was collected
+    #[error("Insufficient data: got {got} of {requested} requested")]
+    InsufficientData { got: usize, requested: usize },
+
+    /// A startup health test rejected the source output
+    #[error("Health check failed for {source_name}: {reason}")]
+    HealthCheckFailed { source_name: String, reason: String },
+
+    /// The serial port kept failing after every retry and reconnect
+    #[error("Serial port {port} failed after {retries} retries: {reason}")]
+    SerialTimeout { port: String, retries: u32, reason: String },
+
+    /// A TCP noise source kept dropping the connection after every retry and reconnect
+    #[error("Connection to {addr} failed after {retries} retries: {reason}")]
+    ConnectionLost { addr: String, retries: u32, reason: String },
+
+    /// Collection did not finish within its overall deadline
+    #[error("Collection timed out after {timeout:?}: got {got} of {requested} bits")]
+    Timeout { got: usize, requested: usize, timeout: Duration },
+
+    /// The source delivered no bits for longer than the stall timeout
+    #[error("No bits from {source_name} for {timeout:?} ({got} bits were gathered); the device appears to have stalled")]
+    Stalled { source_name: String, got: usize, timeout: Duration },
+
+    /// The webcam keeps returning the same frame
+    #[error("Webcam returned {0} identical frames in a row; the camera appears to be frozen")]
+    CameraFrozen(usize),
+
+    /// A file source has no more bytes to replay
+    #[error("End of input file {} reached", .0.display())]
+    EndOfInput(PathBuf),
+
+    /// The webcam read the --max-frames budget of the current collection
+    #[error("Frame limit of {0} frames reached")]
+    FrameLimit(usize),
+
+    /// A quality gate rejected every window for too long
+    #[error("Quality gate rejected {windows} windows in a row (entropy below {min_entropy}); the source appears to have degraded")]
+    QualityGateRejected { windows: usize, min_entropy: f64 },
+
+    /// Every source in an entropy pool failed or could not be started
+    #[error("{0}")]
+    PoolExhausted(String),
+
+    #[error("Camera error: {0}")]
+    Camera(#[from] opencv::Error),
+
+    /// Errors from the audio backend, which uses a separate type per operation
+    #[error("Audio error: {0}")]
+    Audio(String),
+
+    #[error("Serial port error: {0}")]
+    Serial(#[from] serialport::Error),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+impl QrngError {
+    /// Whether the error means the source has nothing more to give this collection,
+    /// so the bits gathered so far are the result rather than a failure
+    pub fn is_end_of_input(&self) -> bool {
+        matches!(self, QrngError::EndOfInput(_) | QrngError::FrameLimit(_))
+    }
+
+    /// Whether the error means the device is absent, as opposed to failing while in use
+    pub fn is_no_device(&self) -> bool {
+        match self {
+            QrngError::NoDevice(_) => true,
+            QrngError::Serial(e) => e.kind() == serialport::ErrorKind::NoDevice,
+            _ => false,
+        }
+    }
+}
+
+/// Result type returned by the entropy sources
+pub type Result<T> = std::result::Result<T, QrngError>;
+
+macro_rules! audio_error_from {
+    ($($error:ty),* $(,)?) => {
+        $(
+            impl From<$error> for QrngError {
+                fn from(error: $error) -> Self {
+                    QrngError::Audio(error.to_string())
+                }
+            }
+        )*
+    };
+}
+
+audio_error_from!(
+    cpal::DevicesError,
+    cpal::DeviceNameError,
+    cpal::DefaultStreamConfigError,
+    cpal::BuildStreamError,
+    cpal::PlayStreamError,
+);
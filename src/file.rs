@@ -0,0 +1,74 @@
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+
+use crate::error::{QrngError, Result};
+use crate::utils;
+use crate::QrngSource;
+
+/// Number of bytes read from the file per chunk
+const FILE_CHUNK_BYTES: usize = 4096;
+
+/// Deterministic source replaying bytes from a file
+///
+/// Each byte is expanded to 8 bits with `utils::bytes_to_bits`, so known
+/// vectors or captured hardware samples can be pushed through the same
+/// post-processing as live noise.
+pub struct FileSource {
+    path: PathBuf,
+    /// Byte at which replay starts
+    offset: u64,
+    reader: Option<BufReader<File>>,
+    exhausted: bool,
+}
+
+impl FileSource {
+    /// # Arguments
+    /// * `path` - File whose bytes are used as raw noise
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self::with_offset(path, 0)
+    }
+
+    /// # Arguments
+    /// * `path` - File whose bytes are used as raw noise
+    /// * `offset` - Number of leading bytes to skip
+    pub fn with_offset(path: impl Into<PathBuf>, offset: u64) -> Self {
+        FileSource {
+            path: path.into(),
+            offset,
+            reader: None,
+            exhausted: false,
+        }
+    }
+}
+
+impl QrngSource for FileSource {
+    /// Read the next block of bytes from the file
+    ///
+    /// Fails with [`QrngError::EndOfInput`] once the end of the file has been
+    /// reached; a collection then ends with the bits read so far.
+    fn read_chunk(&mut self) -> Result<Vec<u8>> {
+        if self.exhausted {
+            return Err(QrngError::EndOfInput(self.path.clone()));
+        }
+
+        if self.reader.is_none() {
+            let mut file = File::open(&self.path)
+                .map_err(|e| QrngError::NoDevice(format!("Failed to open input file {}: {}", self.path.display(), e)))?;
+            if self.offset > 0 {
+                file.seek(SeekFrom::Start(self.offset))?;
+            }
+            self.reader = Some(BufReader::new(file));
+        }
+        let reader = self.reader.as_mut().expect("file opened above");
+
+        let mut buffer = [0u8; FILE_CHUNK_BYTES];
+        let count = reader.read(&mut buffer)?;
+        if count == 0 {
+            self.exhausted = true;
+            return Err(QrngError::EndOfInput(self.path.clone()));
+        }
+
+        Ok(utils::bytes_to_bits(&buffer[..count], utils::BitOrder::default()))
+    }
+}
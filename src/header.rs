@@ -0,0 +1,148 @@
+//! Self-describing header for binary output files
+//!
+//! A plain output file is only packed bits. With a header the file also
+//! records where the bits came from and how they were processed, and readers
+//! that know the layout (`verify`) can skip it. Files without a header are
+//! unchanged, so external tools keep working on the default output.
+//!
+//! # Layout
+//!
+//! All integers are little-endian.
+//!
+//! | Offset | Size | Field |
+//! |--------|------|-------|
+//! | 0      | 4    | Magic `QRNG` (`51 52 4E 47`) |
+//! | 4      | 1    | Format version, currently 1 |
+//! | 5      | 1    | Flags (below) |
+//! | 6      | 2    | Metadata length `N` in bytes |
+//! | 8      | 8    | Number of valid bits in the body |
+//! | 16     | `N`  | Metadata: a UTF-8 JSON object |
+//! | 16 + `N` | rest | Body: the bits, packed 8 per byte |
+//!
+//! Flag bits, from the least significant:
+//!
+//! * 0 - the bits were debiased by a randomness extractor
+//! * 1 - the bits were whitened (XOR-folded or hashed)
+//! * 2 - the whitening hash was keyed (HMAC)
+//! * 3 - the body is packed most significant bit first (otherwise least significant first)
+//!
+//! The remaining flag bits are zero. The metadata object has a `source`
+//! string describing the generator and a `params` array holding the command
+//! line that produced the file. The last body byte is zero-padded when the bit
+//! count is not a multiple of 8.
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{QrngError, Result};
+use crate::utils::BitOrder;
+
+/// First four bytes of a file with a header
+pub const MAGIC: [u8; 4] = *b"QRNG";
+
+/// Header format version written by this crate
+pub const VERSION: u8 = 1;
+
+/// Length of the fixed part of the header, before the metadata
+const FIXED_LEN: usize = 16;
+
+const FLAG_DEBIASED: u8 = 1 << 0;
+const FLAG_WHITENED: u8 = 1 << 1;
+const FLAG_KEYED: u8 = 1 << 2;
+const FLAG_MSB_FIRST: u8 = 1 << 3;
+
+/// Free-form part of the header
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct Metadata {
+    source: String,
+    params: Vec<String>,
+}
+
+/// Contents of an output file header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputHeader {
+    /// Number of valid bits in the body
+    pub bit_count: u64,
+    pub debiased: bool,
+    pub whitened: bool,
+    pub keyed: bool,
+    /// Packing order of the body
+    pub bit_order: BitOrder,
+    /// Description of the generator, e.g. "webcam 0 quantum noise"
+    pub source: String,
+    /// Command line that produced the file
+    pub params: Vec<String>,
+}
+
+impl OutputHeader {
+    /// Serialize the header, ready to be followed by the body
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut metadata = Metadata { source: self.source.clone(), params: self.params.clone() };
+        // The length field is 16 bits; an absurdly long command line is dropped rather than the header
+        if serde_json::to_vec(&metadata).map_or(0, |json| json.len()) > u16::MAX as usize {
+            metadata.params.clear();
+        }
+        let metadata = serde_json::to_vec(&metadata).expect("metadata of strings always serializes");
+
+        let mut flags = 0;
+        for (set, flag) in [(self.debiased, FLAG_DEBIASED), (self.whitened, FLAG_WHITENED), (self.keyed, FLAG_KEYED)] {
+            if set {
+                flags |= flag;
+            }
+        }
+        if self.bit_order == BitOrder::MsbFirst {
+            flags |= FLAG_MSB_FIRST;
+        }
+
+        let mut bytes = Vec::with_capacity(FIXED_LEN + metadata.len());
+        bytes.extend_from_slice(&MAGIC);
+        bytes.push(VERSION);
+        bytes.push(flags);
+        bytes.extend_from_slice(&(metadata.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(&self.bit_count.to_le_bytes());
+        bytes.extend_from_slice(&metadata);
+        bytes
+    }
+
+    /// Split the contents of a file into its header and body
+    ///
+    /// # Returns
+    /// `None` if the file does not start with the magic (a plain output file),
+    /// otherwise the header and the body bytes following it
+    pub fn parse(bytes: &[u8]) -> Result<Option<(OutputHeader, &[u8])>> {
+        if !bytes.starts_with(&MAGIC) {
+            return Ok(None);
+        }
+        if bytes.len() < FIXED_LEN {
+            return Err(QrngError::UnsupportedFormat(format!("header truncated to {} bytes", bytes.len())));
+        }
+        let version = bytes[4];
+        if version != VERSION {
+            return Err(QrngError::UnsupportedFormat(format!("header version {version} (this build reads version {VERSION})")));
+        }
+        let flags = bytes[5];
+        let metadata_len = u16::from_le_bytes([bytes[6], bytes[7]]) as usize;
+        let bit_count = u64::from_le_bytes(bytes[8..16].try_into().expect("slice of 8 bytes"));
+
+        let body_start = FIXED_LEN + metadata_len;
+        if bytes.len() < body_start {
+            return Err(QrngError::UnsupportedFormat(format!("header metadata truncated ({} of {metadata_len} bytes)", bytes.len() - FIXED_LEN)));
+        }
+        let metadata: Metadata = serde_json::from_slice(&bytes[FIXED_LEN..body_start])
+            .map_err(|e| QrngError::UnsupportedFormat(format!("header metadata: {e}")))?;
+        let body = &bytes[body_start..];
+        if bit_count > body.len() as u64 * 8 {
+            return Err(QrngError::UnsupportedFormat(format!("header claims {bit_count} bits but the body holds {}", body.len() * 8)));
+        }
+
+        let header = OutputHeader {
+            bit_count,
+            debiased: flags & FLAG_DEBIASED != 0,
+            whitened: flags & FLAG_WHITENED != 0,
+            keyed: flags & FLAG_KEYED != 0,
+            bit_order: if flags & FLAG_MSB_FIRST != 0 { BitOrder::MsbFirst } else { BitOrder::LsbFirst },
+            source: metadata.source,
+            params: metadata.params,
+        };
+        Ok(Some((header, body)))
+    }
+}
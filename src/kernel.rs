@@ -0,0 +1,44 @@
+//! Crediting collected entropy to the Linux kernel pool
+
+use std::fs::OpenOptions;
+use std::io;
+use std::os::unix::io::AsRawFd;
+
+/// Device accepting the `RNDADDENTROPY` ioctl
+pub const RANDOM_DEVICE: &str = "/dev/random";
+
+/// Mix bytes into the kernel entropy pool and credit them with entropy
+///
+/// Unlike a plain write to `/dev/random`, which mixes the bytes in without
+/// crediting them, the `RNDADDENTROPY` ioctl raises the kernel's entropy
+/// estimate. It requires `CAP_SYS_ADMIN`.
+///
+/// # Arguments
+/// * `bytes` - Conditioned random bytes
+/// * `entropy_bits` - Entropy to credit, capped at 8 bits per byte
+///
+/// # Returns
+/// The number of entropy bits credited
+pub fn add_entropy(bytes: &[u8], entropy_bits: usize) -> io::Result<usize> {
+    let device = OpenOptions::new().write(true).open(RANDOM_DEVICE)?;
+    let entropy_bits = entropy_bits.min(bytes.len() * 8);
+    
+    // struct rand_pool_info { int entropy_count; int buf_size; __u32 buf[]; }
+    let mut info = vec![0u32; 2 + bytes.len().div_ceil(4)];
+    info[0] = entropy_bits as u32;
+    info[1] = bytes.len() as u32;
+    for (word, chunk) in info[2..].iter_mut().zip(bytes.chunks(4)) {
+        let mut buffer = [0u8; 4];
+        buffer[..chunk.len()].copy_from_slice(chunk);
+        *word = u32::from_ne_bytes(buffer);
+    }
+    
+    // SAFETY: `info` is a valid, suitably aligned rand_pool_info whose buffer
+    // holds `buf_size` bytes, and it outlives the call
+    let result = unsafe { libc::ioctl(device.as_raw_fd(), libc::RNDADDENTROPY, info.as_ptr()) };
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    
+    Ok(entropy_bits)
+}
@@ -0,0 +1,433 @@
+//! Semi-quantum random number generation using consumer hardware
+//!
+//! Each hardware backend (webcam, microphone, Arduino over serial, ESP32 over
+//! TCP) is exposed both as a plain collection function and as a struct
+//! implementing [`QrngSource`], so downstream programs can drive the
+//! generator directly. [`MultiSource`] pools all available backends together.
+//! For deterministic testing, [`FileSource`] replays recorded bytes and
+//! [`MockSource`] generates seeded pseudo-random bits without any hardware.
+//! Failures are reported as [`QrngError`] so callers can tell a missing device
+//! from exhausted data.
+
+pub mod audio;
+pub mod checkpoint;
+pub mod error;
+pub mod file;
+pub mod header;
+#[cfg(target_os = "linux")]
+pub mod kernel;
+pub mod mock;
+pub mod multi;
+pub mod progress;
+pub mod protocol;
+pub mod quality;
+pub mod rate;
+pub mod rng;
+pub mod serial;
+#[cfg(feature = "async")]
+pub mod stream;
+pub mod tcp;
+pub mod utils;
+pub mod watchdog;
+pub mod webcam;
+
+use error::Result;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+pub use audio::{audio_qrng, AudioConfig, AudioSource};
+pub use checkpoint::Checkpoint;
+pub use error::QrngError;
+pub use file::FileSource;
+pub use mock::MockSource;
+pub use multi::MultiSource;
+pub use quality::QualityGate;
+pub use rate::RateLimiter;
+pub use rng::QuantumRng;
+pub use serial::{serial_qrng, SerialConfig, SerialFormat, SerialSource};
+pub use tcp::{tcp_qrng, TcpConfig, TcpSource};
+pub use watchdog::StallWatchdog;
+pub use webcam::{webcam_qrng, WebcamConfig, WebcamSource};
+
+/// Raw sensor units a source has consumed, e.g. 120 frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnitsRead {
+    pub count: usize,
+    /// Singular unit name: "frame", "sample", "line" or "byte"
+    pub unit: &'static str,
+}
+
+/// A hardware entropy source producing raw random bits
+///
+/// Sources keep their device open between calls, so repeated reads (as in
+/// streaming mode) do not pay the device initialization cost again.
+pub trait QrngSource {
+    /// Read the next batch of raw bits available from the device
+    ///
+    /// A batch is whatever the hardware naturally delivers at once: one
+    /// webcam frame, one drained audio buffer, one serial reading. It may be
+    /// empty if the device had nothing ready.
+    ///
+    /// # Returns
+    /// Vector of random bits (0s and 1s as u8)
+    fn read_chunk(&mut self) -> Result<Vec<u8>>;
+
+    /// Collect raw random bits from the source, stopping early on cancellation
+    ///
+    /// # Arguments
+    /// * `num_bits` - Number of random bits to generate
+    /// * `cancel` - Flag checked between reads; once set, the bits gathered so far are returned
+    ///
+    /// # Returns
+    /// Vector of random bits (0s and 1s as u8), shorter than `num_bits` if cancelled
+    fn collect_bits_cancellable(&mut self, num_bits: usize, cancel: &AtomicBool) -> Result<Vec<u8>> {
+        collect_chunks(self, num_bits, cancel)
+    }
+
+    /// Longest time one collection from this source may take, if limited
+    ///
+    /// Honoured by [`collect_chunks`], so the limit also applies when an
+    /// adapter such as [`QualityGate`] drives the source chunk by chunk.
+    fn collection_timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Mark the start of a new collection
+    ///
+    /// Per-collection budgets such as the webcam's frame limit count from
+    /// here (or from the creation of the source), across however many
+    /// collection calls the caller splits the collection into.
+    fn begin_collection(&mut self) {}
+
+    /// Collect raw random bits from the source
+    ///
+    /// # Arguments
+    /// * `num_bits` - Number of random bits to generate
+    ///
+    /// # Returns
+    /// Vector of random bits (0s and 1s as u8)
+    fn collect_bits(&mut self, num_bits: usize) -> Result<Vec<u8>> {
+        self.collect_bits_cancellable(num_bits, &AtomicBool::new(false))
+    }
+
+    /// Collect every raw bit the source produces within a wall-clock window
+    ///
+    /// # Arguments
+    /// * `duration` - Length of the collection window
+    /// * `cancel` - Flag checked between reads
+    ///
+    /// # Returns
+    /// Vector of random bits (0s and 1s as u8)
+    fn collect_for(&mut self, duration: Duration, cancel: &AtomicBool) -> Result<Vec<u8>> {
+        collect_chunks_for(self, duration, cancel)
+    }
+
+    /// Read and throw away the first bits of a freshly opened device
+    ///
+    /// Sensors are non-stationary right after power-on (auto-exposure
+    /// settling, DC offset, ADC stabilization), so the warmup output should
+    /// never reach post-processing.
+    ///
+    /// # Arguments
+    /// * `num_bits` - Number of bits to discard
+    /// * `cancel` - Flag checked between reads
+    ///
+    /// # Returns
+    /// Number of bits actually discarded (fewer than `num_bits` if cancelled
+    /// or the input ran out)
+    fn discard_bits(&mut self, num_bits: usize, cancel: &AtomicBool) -> Result<usize> {
+        let mut discarded = 0;
+        while discarded < num_bits && !cancel.load(Ordering::Relaxed) {
+            match self.read_chunk() {
+                Ok(chunk) => discarded += chunk.len(),
+                Err(e) if e.is_end_of_input() => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(discarded.min(num_bits))
+    }
+
+    /// Expected raw bit throughput, computed from the configuration alone
+    ///
+    /// Never touches the device, so it is a nominal figure (from the baud
+    /// rate, sample rate or resolution) rather than a measurement.
+    ///
+    /// # Returns
+    /// Bits per second, or `None` if the source has no meaningful rate
+    fn estimated_rate_bits_per_sec(&self) -> Option<f64> {
+        None
+    }
+
+    /// Also write the unprocessed sensor data read from now on to `sink`
+    ///
+    /// The layout depends on the source (see each implementation); bit
+    /// extraction is unaffected, so one session yields both.
+    ///
+    /// # Returns
+    /// `false` if the source has no raw data beyond its bits
+    fn set_raw_capture(&mut self, sink: Box<dyn Write>) -> bool {
+        let _ = sink;
+        false
+    }
+
+    /// Sensor units consumed since the source was created, for yield statistics
+    ///
+    /// # Returns
+    /// `None` if the source has no natural unit (files, pools, the mock PRNG)
+    fn units_read(&self) -> Option<UnitsRead> {
+        None
+    }
+}
+
+/// Mutable references to a source are sources too, so adapters such as
+/// [`QualityGate`] can wrap a borrowed source
+impl<S: QrngSource + ?Sized> QrngSource for &mut S {
+    fn read_chunk(&mut self) -> Result<Vec<u8>> {
+        (**self).read_chunk()
+    }
+
+    fn collect_bits_cancellable(&mut self, num_bits: usize, cancel: &AtomicBool) -> Result<Vec<u8>> {
+        (**self).collect_bits_cancellable(num_bits, cancel)
+    }
+
+    fn collect_for(&mut self, duration: Duration, cancel: &AtomicBool) -> Result<Vec<u8>> {
+        (**self).collect_for(duration, cancel)
+    }
+
+    fn discard_bits(&mut self, num_bits: usize, cancel: &AtomicBool) -> Result<usize> {
+        (**self).discard_bits(num_bits, cancel)
+    }
+
+    fn collection_timeout(&self) -> Option<Duration> {
+        (**self).collection_timeout()
+    }
+
+    fn begin_collection(&mut self) {
+        (**self).begin_collection()
+    }
+
+    fn estimated_rate_bits_per_sec(&self) -> Option<f64> {
+        (**self).estimated_rate_bits_per_sec()
+    }
+
+    fn set_raw_capture(&mut self, sink: Box<dyn Write>) -> bool {
+        (**self).set_raw_capture(sink)
+    }
+
+    fn units_read(&self) -> Option<UnitsRead> {
+        (**self).units_read()
+    }
+}
+
+/// Repeatedly read chunks from a source until `num_bits` bits are gathered
+///
+/// Shows a progress bar with an ETA on stderr (see [`progress::set_quiet`]).
+/// Surplus bits from the final chunk are dropped (see [`collect_whole_chunks`]
+/// to keep them). The source's
+/// [`QrngSource::collection_timeout`] applies, and a source that runs out of
+/// input (see [`QrngError::is_end_of_input`]) ends the collection early.
+///
+/// # Arguments
+/// * `source` - Source to read from
+/// * `num_bits` - Number of random bits to generate
+/// * `cancel` - Flag checked between chunks
+///
+/// # Returns
+/// Vector of `num_bits` random bits, or fewer if cancelled or the input ran out
+pub fn collect_chunks<S: QrngSource + ?Sized>(source: &mut S, num_bits: usize, cancel: &AtomicBool) -> Result<Vec<u8>> {
+    let timeout = source.collection_timeout();
+    collect_chunks_within(source, num_bits, timeout, cancel)
+}
+
+/// Like [`collect_chunks`], but give up once an overall deadline passes
+///
+/// The deadline is checked between chunks, so a blocking read can overrun it
+/// by up to one read timeout.
+///
+/// # Arguments
+/// * `source` - Source to read from
+/// * `num_bits` - Number of random bits to generate
+/// * `timeout` - Longest time the whole collection may take (`None` waits forever)
+/// * `cancel` - Flag checked between chunks
+///
+/// # Returns
+/// Vector of `num_bits` random bits, fewer if cancelled or the input ran out,
+/// or [`QrngError::Timeout`] with the number of bits gathered
+pub fn collect_chunks_within<S: QrngSource + ?Sized>(source: &mut S, num_bits: usize, timeout: Option<Duration>, cancel: &AtomicBool) -> Result<Vec<u8>> {
+    let mut bits = gather_chunks(source, num_bits, timeout, cancel)?;
+    bits.truncate(num_bits);
+    Ok(bits)
+}
+
+/// Like [`collect_chunks`], but keep the final chunk whole
+///
+/// For sources that buffer the surplus of their last chunk (a webcam frame)
+/// for the next collection instead of dropping it.
+///
+/// # Arguments
+/// * `source` - Source to read from
+/// * `num_bits` - Number of random bits to generate
+/// * `cancel` - Flag checked between chunks
+///
+/// # Returns
+/// At least `num_bits` random bits, or fewer if cancelled or the input ran out
+pub fn collect_whole_chunks<S: QrngSource + ?Sized>(source: &mut S, num_bits: usize, cancel: &AtomicBool) -> Result<Vec<u8>> {
+    let timeout = source.collection_timeout();
+    gather_chunks(source, num_bits, timeout, cancel)
+}
+
+/// Read chunks until `num_bits` bits are gathered, keeping the final chunk whole
+fn gather_chunks<S: QrngSource + ?Sized>(source: &mut S, num_bits: usize, timeout: Option<Duration>, cancel: &AtomicBool) -> Result<Vec<u8>> {
+    let mut bits = Vec::with_capacity(num_bits);
+    let bar = progress::progress_bar(num_bits, "bits");
+    let start = Instant::now();
+
+    while bits.len() < num_bits {
+        if cancel.load(Ordering::Relaxed) {
+            bar.abandon();
+            log::warn!("Collection interrupted after {} of {} bits", bits.len(), num_bits);
+            break;
+        }
+
+        if let Some(timeout) = timeout.filter(|&timeout| start.elapsed() > timeout) {
+            bar.abandon();
+            return Err(QrngError::Timeout { got: bits.len(), requested: num_bits, timeout });
+        }
+
+        let chunk = match source.read_chunk() {
+            Ok(chunk) => chunk,
+            Err(e) if e.is_end_of_input() => {
+                bar.abandon();
+                log::warn!("{e}: only {} of {} bits were collected", bits.len(), num_bits);
+                break;
+            },
+            Err(e) => return Err(e),
+        };
+        bits.extend(chunk);
+        bar.set_position(bits.len().min(num_bits) as u64);
+    }
+
+    bar.finish_and_clear();
+    Ok(bits)
+}
+
+/// Repeatedly read chunks from a source until a wall-clock deadline passes
+///
+/// The window starts after an initial read that opens the device (its bits
+/// are discarded), so initialization time does not skew throughput; the
+/// final chunk may end slightly past the deadline.
+///
+/// # Arguments
+/// * `source` - Source to read from
+/// * `duration` - Length of the collection window
+/// * `cancel` - Flag checked between chunks
+///
+/// # Returns
+/// Every bit read during the window, or until the input ran out
+pub fn collect_chunks_for<S: QrngSource + ?Sized>(source: &mut S, duration: Duration, cancel: &AtomicBool) -> Result<Vec<u8>> {
+    source.read_chunk()?;
+
+    let mut bits = Vec::new();
+    let start = Instant::now();
+
+    while start.elapsed() < duration && !cancel.load(Ordering::Relaxed) {
+        match source.read_chunk() {
+            Ok(chunk) => bits.extend(chunk),
+            Err(e) if e.is_end_of_input() => {
+                log::warn!("{e}; the window ended early");
+                break;
+            },
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    /// Source that delivers `chunks` chunks of 64 alternating bits, then fails with `end`
+    struct FiniteSource {
+        chunks: usize,
+        end: fn() -> QrngError,
+    }
+
+    impl QrngSource for FiniteSource {
+        fn read_chunk(&mut self) -> Result<Vec<u8>> {
+            if self.chunks == 0 {
+                return Err((self.end)());
+            }
+            self.chunks -= 1;
+            Ok([0, 1].repeat(32))
+        }
+    }
+
+    /// Source that never delivers a bit and limits each collection to 30 ms
+    struct SilentSource;
+
+    impl QrngSource for SilentSource {
+        fn read_chunk(&mut self) -> Result<Vec<u8>> {
+            thread::sleep(Duration::from_millis(5));
+            Ok(Vec::new())
+        }
+
+        fn collection_timeout(&self) -> Option<Duration> {
+            Some(Duration::from_millis(30))
+        }
+    }
+
+    /// Collect `num_bits` from a fresh source directly and behind each adapter
+    fn collect_through_adapters<S: QrngSource>(make: impl Fn() -> S, num_bits: usize) -> Vec<Result<Vec<u8>>> {
+        let cancel = AtomicBool::new(false);
+        vec![
+            make().collect_bits_cancellable(num_bits, &cancel),
+            QualityGate::with_window(make(), 0.5, 64).collect_bits_cancellable(num_bits, &cancel),
+            RateLimiter::new(make(), 1e9).collect_bits_cancellable(num_bits, &cancel),
+            StallWatchdog::new(make(), "test source", Duration::from_secs(10)).collect_bits_cancellable(num_bits, &cancel),
+        ]
+    }
+
+    #[test]
+    fn end_of_input_returns_the_bits_read_so_far() {
+        let ends: [fn() -> QrngError; 2] = [|| QrngError::EndOfInput("input.bin".into()), || QrngError::FrameLimit(3)];
+        for end in ends {
+            for result in collect_through_adapters(|| FiniteSource { chunks: 3, end }, 1000) {
+                assert_eq!(result.unwrap().len(), 192);
+            }
+        }
+    }
+
+    #[test]
+    fn whole_chunks_keep_the_surplus() {
+        let end = || QrngError::EndOfInput("input.bin".into());
+        let cancel = AtomicBool::new(false);
+        assert_eq!(collect_chunks(&mut FiniteSource { chunks: 3, end }, 100, &cancel).unwrap().len(), 100);
+        assert_eq!(collect_whole_chunks(&mut FiniteSource { chunks: 3, end }, 100, &cancel).unwrap().len(), 128);
+    }
+
+    #[test]
+    fn collection_timeout_applies_behind_adapters() {
+        for result in collect_through_adapters(|| SilentSource, 1000) {
+            match result {
+                Err(QrngError::Timeout { got, requested, .. }) => assert_eq!((got, requested), (0, 1000)),
+                other => panic!("expected a timeout, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn gated_file_source_returns_a_short_read() {
+        let path = std::env::temp_dir().join(format!("quantum_rng_short_read_{}.bin", std::process::id()));
+        let bytes: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+        std::fs::write(&path, &bytes).unwrap();
+
+        let mut gate = QualityGate::with_window(FileSource::new(&path), 0.5, 64);
+        let bits = gate.collect_bits_cancellable(20_000, &AtomicBool::new(false));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(bits.unwrap(), utils::bytes_to_bits(&bytes, utils::BitOrder::default()));
+    }
+}
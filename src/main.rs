@@ -1,146 +1,2596 @@
-mod webcam;
-mod audio;
-mod serial;
-mod utils;
+#[cfg(feature = "tui")]
+mod tui;
 
-use clap::{Parser, Subcommand};
-use std::io::Write;
-use std::fs::File;
-use std::time::Instant;
+use quantum_rng::audio::DEFAULT_AUDIO_BITS;
+use quantum_rng::checkpoint::Checkpoint;
+use quantum_rng::header::OutputHeader;
+use quantum_rng::protocol::{ChannelCombine, Endian, StreamProtocol};
+use quantum_rng::serial::{DEFAULT_BAUD_RATE, DEFAULT_MAX_RETRIES, DEFAULT_READ_TIMEOUT};
+use quantum_rng::tcp;
+use quantum_rng::webcam::{WebcamChannels, WebcamExtract, DEFAULT_FRAME_HASH_BITS, DEFAULT_HEIGHT, DEFAULT_WIDTH};
+use quantum_rng::utils::{BitOrder, HashAlgo, Quality};
+use quantum_rng::quality::{DEFAULT_QUALITY_WINDOW_BITS, MIN_QUALITY_WINDOW_BITS};
+use quantum_rng::watchdog::StallState;
+use quantum_rng::{utils, AudioConfig, AudioSource, FileSource, MockSource, MultiSource, QrngError, QrngSource, QualityGate, RateLimiter, StallWatchdog, SerialConfig, SerialFormat, SerialSource, TcpConfig, TcpSource, UnitsRead, WebcamConfig, WebcamSource};
+
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::io::{ErrorKind, Write};
+use std::fs::OpenOptions;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Number of bits packed and written per write when streaming to stdout
+const STREAM_CHUNK_BITS: usize = 4096;
+
+/// Raw bits per block in --low-latency mode
+const LOW_LATENCY_BLOCK_BITS: usize = 256;
+
+/// Number of initial bits run through the startup health tests
+const HEALTH_CHECK_BITS: usize = utils::APT_WINDOW_SIZE;
+
+/// Collection attempts made before giving up on reaching --min-entropy
+const MIN_ENTROPY_ATTEMPTS: usize = 3;
+
+/// Number of webcam indices probed by `list-devices`
+const LIST_CAMERA_INDICES: i32 = 10;
+
+/// Entropy margin (in bits) collected beyond the seed size, as SP 800-90C requires for full-entropy output
+const SEED_ENTROPY_MARGIN_BITS: usize = 64;
+
+/// Size of the quantum seed collected for --expand
+const EXPAND_SEED_BITS: usize = 256;
+
+/// Label attached to --expand output so it is never mistaken for raw hardware bits
+const EXPANDED_OUTPUT_LABEL: &str = "seeded CSPRNG, not raw quantum";
+
+/// Label attached to `mock` output so test data is never mistaken for hardware bits
+const MOCK_OUTPUT_LABEL: &str = "mock ChaCha20 PRNG, not quantum";
+
+/// How often a read blocked in the device driver is checked for with --stall-timeout-s
+const STALL_MONITOR_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Rows aimed for when --bias-csv picks its own block size
+const BIAS_CSV_TARGET_BLOCKS: usize = 1000;
+
+/// Smallest automatic --bias-csv block, below which sampling noise swamps drift
+const BIAS_CSV_MIN_BLOCK_BITS: usize = 64;
+
+/// Raw bits collected between checks of whether the --checkpoint file is due to be saved
+const CHECKPOINT_CHUNK_BITS: usize = 4096;
+
+/// Minimum time between saves of the --checkpoint file
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Prefix of the environment variables that set option defaults, e.g. QRNG_PORT
+const ENV_PREFIX: &str = "QRNG_";
+
+/// Lowest per-bit min-entropy estimate trusted when sizing seed collection
+const SEED_MIN_ENTROPY_FLOOR: f64 = 0.01;
+
+/// Raw bits gathered before each Toeplitz extraction in chunked mode, so the
+/// 128-bit security margin is paid on large blocks rather than on every chunk
+const TOEPLITZ_MIN_BLOCK_BITS: usize = 4096;
 
 #[derive(Parser)]
 #[command(author, version, about = "Quantum Random Number Generator using consumer hardware")]
 struct Cli {
+    /// Only log warnings and errors, and hide the progress bar (always hidden when stderr is not a terminal)
+    #[arg(short, long, global = true, default_value_t = false, conflicts_with = "verbose")]
+    quiet: bool,
+    
+    /// Log more detail: -v for debug messages, -vv to also trace every pooled bit (RUST_LOG overrides)
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+    
     #[command(subcommand)]
     command: Commands,
 }
 
 #[derive(Subcommand)]
 enum Commands {
-    Webcam { /// Generate random bits using webcam quantum noise
-        #[arg(short, long, default_value_t = 1024)]
-        num_bits: usize,
+    /// Generate random bits using webcam quantum noise
+    Webcam {
+        /// OpenCV camera index (see your system's video device list)
+        #[arg(long, default_value_t = 0)]
+        camera_index: i32,
+        
+        /// Requested frame width (the driver may clamp it)
+        #[arg(long, default_value_t = DEFAULT_WIDTH)]
+        width: u32,
+        
+        /// Requested frame height (the driver may clamp it)
+        #[arg(long, default_value_t = DEFAULT_HEIGHT)]
+        height: u32,
+        
+        /// Pixel bit planes to harvest, e.g. "0", "0-2" or "0,2" (plane 0 is the LSB)
+        #[arg(long, default_value = "0", value_parser = parse_bit_planes)]
+        bit_planes: u8,
+        
+        /// XOR inter-frame timing jitter into the pixel bits (needs a sub-millisecond OS clock)
+        #[arg(long, default_value_t = false)]
+        mix_timing: bool,
+        
+        /// XOR each pixel with its left neighbour to cancel fixed-pattern noise (costs one bit per row)
+        #[arg(long, default_value_t = false)]
+        webcam_diff: bool,
+        
+        /// Stop after this many frames even if --num-bits was not reached, logging each frame's bit count
+        #[arg(long)]
+        max_frames: Option<usize>,
+        
+        /// Pause between frame reads so buffering cameras deliver fresh frames (about 33 for 30 fps UVC cameras)
+        #[arg(long, default_value_t = 0)]
+        frame_delay_ms: u64,
+        
+        /// Harvest grayscale pixels, or each color channel separately (~3x the bits; channels share some
+        /// fixed-pattern noise, so pair rgb with --webcam-diff)
+        #[arg(long, value_enum, default_value_t = WebcamChannels::Gray)]
+        webcam_channels: WebcamChannels,
+        
+        /// Take bits from pixel bit planes, or squeeze --frame-hash-bits from a SHAKE256 hash of each whole frame
+        /// (resists fixed-pattern noise, assuming every frame holds that much min-entropy)
+        #[arg(long, value_enum, default_value_t = WebcamExtract::Lsb)]
+        webcam_extract: WebcamExtract,
+        
+        /// Bits per frame with --webcam-extract frame-hash
+        #[arg(long, default_value_t = DEFAULT_FRAME_HASH_BITS, value_parser = clap::value_parser!(u64).range(1..).map(|bits| bits as usize))]
+        frame_hash_bits: usize,
+        
+        /// Harvest only every n-th pixel of each frame, skipping neighbours that share readout circuitry
+        /// (n times fewer bits; an n that does not divide --width also varies the columns between rows)
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..).map(|stride| stride as usize))]
+        pixel_stride: usize,
+        
+        /// XOR this many consecutive frames together before extracting, cancelling static fixed-pattern noise
+        /// (use an even number; bits arrive n times less often, so allow n frames within --stall-timeout-s)
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..).map(|frames| frames as usize))]
+        frame_xor: usize,
         
+        #[command(flatten)]
+        args: CollectArgs,
+    },
+    
+    /// Generate random bits using microphone quantum noise
+    Audio {
+        /// Number of low bits harvested per sample (values above 4 risk leaking audible signal)
+        #[arg(long, default_value_t = DEFAULT_AUDIO_BITS, value_parser = clap::value_parser!(u8).range(1..=16))]
+        audio_bits: u8,
+        
+        /// Keep only every k-th sample to break up periodic interference such as mains hum (records k times longer)
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+        decimate: u64,
+        
+        /// Input device to record from, by full name or part of it (see `list-devices`; falls back to the default)
+        #[arg(long)]
+        audio_device: Option<String>,
+        
+        #[command(flatten)]
+        args: CollectArgs,
+    },
+    
+    /// Generate random bits using Arduino-based quantum noise
+    Serial {
         #[arg(short, long)]
-        output_file: Option<String>,
+        port: String,
+        
+        #[arg(long, default_value_t = DEFAULT_BAUD_RATE)]
+        baud: u32,
+        
+        #[command(flatten)]
+        protocol: ProtocolArgs,
+        
+        /// Consecutive read failures (with exponential backoff and reconnects) before giving up
+        #[arg(long, default_value_t = DEFAULT_MAX_RETRIES)]
+        max_retries: u32,
+        
+        /// Time a single read waits for data before it counts as a failure
+        #[arg(long, default_value_t = DEFAULT_READ_TIMEOUT.as_millis() as u64)]
+        read_timeout_ms: u64,
+        
+        /// Abort with an error if the whole collection takes longer than this
+        #[arg(long)]
+        collection_timeout_s: Option<u64>,
+        
+        #[command(flatten)]
+        args: CollectArgs,
+    },
+    
+    /// Generate random bits from a networked noise source (e.g. an ESP32) streaming over TCP
+    Tcp {
+        /// Address of the device, as host:port
+        #[arg(long)]
+        addr: String,
         
-        #[arg(short='d', long, default_value_t = false)]
-        apply_debiasing: bool,
+        /// Protocol options are the same as for the serial source
+        #[command(flatten)]
+        protocol: ProtocolArgs,
         
-        #[arg(short='s', long, default_value_t = false)]
-        apply_hashing: bool,
+        /// Consecutive read failures (with exponential backoff and reconnects) before giving up
+        #[arg(long, default_value_t = tcp::DEFAULT_MAX_RETRIES)]
+        max_retries: u32,
+        
+        /// Time connecting or a single read waits before it counts as a failure
+        #[arg(long, default_value_t = tcp::DEFAULT_READ_TIMEOUT.as_millis() as u64)]
+        read_timeout_ms: u64,
+        
+        /// Abort with an error if the whole collection takes longer than this
+        #[arg(long)]
+        collection_timeout_s: Option<u64>,
+        
+        #[command(flatten)]
+        args: CollectArgs,
+    },
+    
+    /// Generate random bits by XOR-pooling every available source in parallel
+    Multi {
+        /// Serial port of an Arduino generator to include in the pool (-vv logs which sources made each bit)
+        #[arg(short, long)]
+        port: Option<String>,
+        
+        #[command(flatten)]
+        args: CollectArgs,
     },
     
-    Audio {/// Generate random bits using microphone quantum noise
-        #[arg(short, long, default_value_t = 1024)]
-        num_bits: usize,
+    /// Use the first source that is present: webcam, then microphone, then the Arduino on --port
+    Auto {
+        /// Serial port of an Arduino generator tried when neither webcam nor microphone is present
+        #[arg(short, long)]
+        port: Option<String>,
         
+        #[command(flatten)]
+        args: CollectArgs,
+    },
+    
+    /// Replay bytes from a file as noise (deterministic, for testing the pipeline)
+    File {
+        /// File whose bytes are used as raw noise
         #[arg(short, long)]
-        output_file: Option<String>,
+        input: String,
         
-        #[arg(short='d', long, default_value_t = false)]
-        apply_debiasing: bool,
+        #[command(flatten)]
+        args: CollectArgs,
+    },
+    
+    /// Generate deterministic pseudo-random bits from a seeded ChaCha20 PRNG (for CI, not quantum)
+    #[command(hide = true)]
+    Mock {
+        /// PRNG seed; the same seed always produces the same output
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
         
-        #[arg(short='s', long, default_value_t = false)]
-        apply_hashing: bool,
+        #[command(flatten)]
+        args: CollectArgs,
     },
     
-    Serial {/// Generate random bits using Arduino-based quantum noise
+    /// Run the statistical test battery on an existing file (exit code 1 if any test fails)
+    Verify {
+        /// File of packed random bytes to check
         #[arg(short, long)]
-        port: String,
+        input: String,
+        
+        /// Bit packing order used when the file was written
+        #[arg(long, value_enum, default_value_t = BitOrder::LsbFirst)]
+        bit_order: BitOrder,
+    },
+    
+    /// Run the test battery on two files and show which scores better on each metric
+    Compare {
+        /// First file of packed random bytes
+        a: String,
         
-        #[arg(short, long, default_value_t = 1024)]
-        num_bits: usize,
+        /// Second file of packed random bytes
+        b: String,
         
+        /// Bit packing order used when the files were written
+        #[arg(long, value_enum, default_value_t = BitOrder::LsbFirst)]
+        bit_order: BitOrder,
+        
+        /// Compare files of different lengths (p-values of the longer file are more sensitive)
+        #[arg(long, default_value_t = false)]
+        allow_length_mismatch: bool,
+    },
+    
+    /// List cameras, audio inputs and serial ports usable as sources
+    ListDevices,
+    
+    /// Measure the entropy throughput of a source
+    Benchmark {
+        #[arg(long, value_enum)]
+        source: SourceKind,
+        
+        /// Serial port (required for the serial source, optional for multi)
         #[arg(short, long)]
-        output_file: Option<String>,
+        port: Option<String>,
         
-        #[arg(short='d', long, default_value_t = false)]
-        apply_debiasing: bool,
+        /// Collection window in seconds
+        #[arg(long, default_value_t = 10)]
+        duration: u64,
         
-        #[arg(short='s', long, default_value_t = false)]
-        apply_hashing: bool,
+        /// Print the results as JSON instead of a table
+        #[arg(long, default_value_t = false)]
+        json: bool,
     },
 }
 
-fn main() -> anyhow::Result<()> {
-    let cli = Cli::parse();
+/// Hardware source selectable by name
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum SourceKind {
+    Webcam,
+    Audio,
+    Serial,
+    Multi,
+}
+
+impl SourceKind {
+    /// Build the source with default settings
+    fn build(&self, port: Option<&str>) -> anyhow::Result<Box<dyn QrngSource>> {
+        Ok(match self {
+            SourceKind::Webcam => Box::new(WebcamSource::new()),
+            SourceKind::Audio => Box::new(AudioSource::new()),
+            SourceKind::Serial => {
+                let port = port.ok_or_else(|| anyhow::anyhow!("The serial source requires --port"))?;
+                Box::new(SerialSource::new(port))
+            },
+            SourceKind::Multi => Box::new(MultiSource::new(port, false)),
+        })
+    }
+}
+
+/// Throughput of one pipeline stage
+#[derive(Serialize)]
+struct StageRate {
+    stage: &'static str,
+    bits: usize,
+    bits_per_sec: f64,
+}
+
+/// Results of the benchmark subcommand
+#[derive(Serialize)]
+struct BenchmarkResult {
+    source: String,
+    duration_secs: f64,
+    stages: Vec<StageRate>,
+}
+
+/// Wire protocol options shared by the serial and TCP sources
+#[derive(Args, Clone)]
+struct ProtocolArgs {
+    /// Wire format sent by the firmware
+    #[arg(long, value_enum, default_value_t = SerialFormat::AsciiBit)]
+    format: SerialFormat,
+    
+    /// Separator between several readings per line, e.g. "," or " " (default: one reading per line)
+    #[arg(long)]
+    delimiter: Option<char>,
+    
+    /// Channel tags of multi-channel firmware sending "CHANNEL:BIT" readings, e.g. "A,B"
+    #[arg(long, value_delimiter = ',')]
+    channels: Vec<String>,
+    
+    /// How readings from --channels are merged
+    #[arg(long, value_enum, default_value_t = ChannelCombine::Interleave, requires = "channels")]
+    combine: ChannelCombine,
+    
+    /// Bytes per unsigned raw sample with --format raw-byte, e.g. 2 for 10-bit ADC readings
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..=2))]
+    sample_bytes: u8,
+    
+    /// Byte order of 2-byte raw samples
+    #[arg(long, value_enum, default_value_t = Endian::Le)]
+    endian: Endian,
+}
+
+impl ProtocolArgs {
+    fn protocol(&self) -> anyhow::Result<StreamProtocol> {
+        if self.sample_bytes > 1 && self.format != SerialFormat::RawByte {
+            return Err(anyhow::anyhow!("--sample-bytes {} needs --format raw-byte", self.sample_bytes));
+        }
+        Ok(StreamProtocol {
+            format: self.format,
+            delimiter: self.delimiter,
+            channels: self.channels.clone(),
+            combine: self.combine,
+            sample_bytes: self.sample_bytes,
+            endian: self.endian,
+        })
+    }
+}
+
+/// Options shared by every collection subcommand
+#[derive(Args, Clone)]
+struct CollectArgs {
+    #[arg(short, long, default_value_t = 1024)]
+    num_bits: usize,
+    
+    #[arg(short, long)]
+    output_file: Option<String>,
+    
+    /// Credit the output to the kernel entropy pool via RNDADDENTROPY instead of writing a file (Linux, needs root)
+    #[arg(long, default_value_t = false, conflicts_with_all = ["output_file", "buffer_bits", "stream", "fifo"])]
+    feed_kernel: bool,
+    
+    /// Print the final bits to stdout as a 0/1 string (works with or without --output-file)
+    #[arg(long, default_value_t = false)]
+    print: bool,
+    
+    /// Append to the output file instead of truncating it
+    #[arg(long, default_value_t = false)]
+    append: bool,
+    
+    /// Output file encoding (default: binary when the bit count is a multiple of 8, else text)
+    #[arg(long, value_enum)]
+    encoding: Option<Encoding>,
+    
+    /// Bit packing order for binary, hex and base64 output files
+    #[arg(long, value_enum, default_value_t = BitOrder::LsbFirst)]
+    bit_order: BitOrder,
+    
+    /// Write the output file in exactly the layout the NIST STS harness reads
+    #[arg(long, value_enum, requires = "output_file", conflicts_with_all = ["encoding", "bit_order", "stream", "fifo"])]
+    output_format: Option<OutputFormat>,
+    
+    #[arg(short='d', long, default_value_t = false)]
+    apply_debiasing: bool,
+    
+    /// XOR the collected bits with bits from this pool file before debiasing (cycled if shorter)
+    #[arg(long)]
+    mix_file: Option<String>,
+    
+    /// XOR the conditioned output with the OS CSPRNG, so it is never weaker than the OS generator
+    /// even if the sensor is completely broken (the result is then no longer purely quantum)
+    #[arg(long, default_value_t = false, conflicts_with_all = ["stream", "fifo", "seed_bits", "expand"])]
+    mix_os_rng: bool,
+    
+    /// Preset post-processing; overrides --apply-debiasing, --extractor, --apply-hashing and --whitening
+    #[arg(long, value_enum)]
+    profile: Option<Profile>,
+    
+    /// Debiasing algorithm (implies --apply-debiasing)
+    #[arg(long, value_enum)]
+    extractor: Option<Extractor>,
+    
+    /// Bits per symbol for --extractor elias (2 behaves like classic; larger is closer to the entropy bound)
+    #[arg(long, default_value_t = utils::ELIAS_DEFAULT_SYMBOL_BITS, value_parser = clap::value_parser!(u64).range(2..=utils::ELIAS_MAX_SYMBOL_BITS as u64).map(|bits| bits as usize))]
+    symbol_bits: usize,
+    
+    #[arg(short='s', long, default_value_t = false)]
+    apply_hashing: bool,
+    
+    /// Provenance label recorded in reports for output that is not from quantum hardware
+    #[arg(skip)]
+    generator: Option<&'static str>,
+    
+    /// Hash function used by --apply-hashing and --whitening sha3
+    #[arg(long, value_enum, default_value_t = HashAlgo::Sha3_256)]
+    hash: HashAlgo,
+    
+    /// Hash with SHAKE256 to exactly this many output bits (implies --apply-hashing)
+    #[arg(long)]
+    output_bits: Option<usize>,
+    
+    /// Print COUNT uniform integers in [MIN, MAX] made from the conditioned bits, one per line
+    /// (rejection sampling, no modulo bias; write a negative MIN as --integers=-5:5:10)
+    #[arg(
+        long, value_name = "MIN:MAX:COUNT", value_parser = parse_integer_range, allow_hyphen_values = true,
+        conflicts_with_all = ["stdout_binary", "stream", "fifo", "seed_bits", "expand", "buffer_bits", "low_latency"]
+    )]
+    integers: Option<IntegerRange>,
+    
+    /// Hex key making hashing HMAC-SHA3-256, so each application derives its own bits (a label, not a secret; implies --apply-hashing)
+    #[arg(long, value_name = "HEX", value_parser = parse_hex_key)]
+    key: Option<Vec<u8>>,
+    
+    /// Conditioner applied after debiasing (overrides --apply-hashing)
+    #[arg(long, value_enum)]
+    whitening: Option<Whitening>,
+    
+    /// Number of halving passes for --whitening xor-fold
+    #[arg(long, default_value_t = 1)]
+    fold_passes: usize,
+    
+    /// Print a SHAKE256-conditioned full-entropy seed of this many bits as hex (ignores --num-bits)
+    #[arg(long, conflicts_with = "stream")]
+    seed_bits: Option<usize>,
+    
+    /// Stretch a 256-bit quantum seed to this many bits with SHA3 counter mode (seeded CSPRNG output)
+    #[arg(long, conflicts_with_all = ["stream", "seed_bits"])]
+    expand: Option<usize>,
+    
+    /// Process and write the output in chunks of this many raw bits instead of all at once
+    ///
+    /// Peak memory is then a few times this many bytes regardless of --num-bits.
+    /// Each chunk is conditioned independently (SHA3-256 yields 256 bits per chunk),
+    /// and Toeplitz extraction waits for at least 4096 raw bits.
+    #[arg(long, requires = "output_file", conflicts_with_all = ["output_bits", "min_entropy", "report_json", "print", "strict"])]
+    buffer_bits: Option<usize>,
+    
+    /// Collect this many independent batches, numbering the output files (batch.bin -> batch_000.bin, ...)
+    #[arg(long, requires = "output_file", conflicts_with_all = ["stream", "fifo", "seed_bits", "feed_kernel"])]
+    repeat: Option<usize>,
+    
+    /// Repeat --discard-initial before every batch of --repeat, not just the first
+    #[arg(long, default_value_t = false, requires = "repeat")]
+    rewarm: bool,
+    
+    /// Write each block of conditioned output as soon as it is ready instead of after all --num-bits
+    ///
+    /// Uses blocks of 256 raw bits (or --buffer-bits). Debiasing and hashing then
+    /// work per block, so SHA3-256 turns every block into 256 bits; prefer
+    /// --extractor alone or SHAKE256 with --profile paranoid here.
+    #[arg(long, default_value_t = false, requires = "output_file", conflicts_with_all = ["output_bits", "min_entropy", "report_json", "print", "strict"])]
+    low_latency: bool,
+    
+    /// Print the expected collection time from the source's nominal rate and exit without opening the device
+    #[arg(long, default_value_t = false)]
+    dry_run: bool,
+    
+    /// Collect for --duration seconds, debias, print the raw and usable bit counts and rates, and save nothing
+    #[arg(
+        long, default_value_t = false,
+        conflicts_with_all = [
+            "dry_run", "output_file", "raw_capture", "stream", "fifo", "stdout_binary", "seed_bits", "expand",
+            "repeat", "report_json", "print", "feed_kernel", "tui", "bias_csv", "integers"
+        ]
+    )]
+    count_only: bool,
+    
+    /// Collection window in seconds for --count-only
+    #[arg(long, default_value_t = 10, requires = "count_only", value_parser = clap::value_parser!(u64).range(1..))]
+    duration: u64,
+    
+    /// Also save the unprocessed sensor data to this file (layout per source in the README)
+    #[arg(long, value_name = "PATH", conflicts_with = "dry_run")]
+    raw_capture: Option<String>,
+    
+    /// Write an endless stream of packed random bytes to stdout (ignores --num-bits)
+    #[arg(long, default_value_t = false)]
+    stream: bool,
+    
+    /// Write the conditioned bits to stdout as packed bytes (log messages always go to stderr)
+    #[arg(
+        long, visible_alias = "output-stdout-binary", default_value_t = false,
+        conflicts_with_all = ["output_file", "feed_kernel", "print", "stream", "fifo", "encoding", "output_format", "seed_bits", "expand", "buffer_bits", "low_latency"]
+    )]
+    stdout_binary: bool,
+    
+    /// Start the binary output file with a header recording the source, settings and bit count (read by `verify`)
+    #[arg(
+        long, default_value_t = false, requires = "output_file",
+        conflicts_with_all = ["append", "buffer_bits", "low_latency", "fifo", "stream", "encoding", "output_format", "seed_bits", "expand"]
+    )]
+    with_header: bool,
+    
+    /// Write OUTPUT.meta.json next to the output file with the source, settings, versions and statistics report
+    #[arg(long, default_value_t = false, requires = "output_file", conflicts_with_all = ["buffer_bits", "low_latency", "seed_bits", "expand", "fifo"])]
+    annotate: bool,
+    
+    /// Show a live dashboard of bias, entropy and throughput while collecting (needs the tui feature)
+    #[arg(long, default_value_t = false, conflicts_with_all = ["stream", "fifo", "buffer_bits", "low_latency", "stdout_binary", "seed_bits", "expand", "print"])]
+    tui: bool,
+    
+    /// Write the output file incrementally as raw packed bytes, at the reader's pace (automatic for FIFOs)
+    #[arg(long, default_value_t = false)]
+    fifo: bool,
+    
+    /// Minimum Shannon entropy (bits/bit) required of the raw bits; re-collects, then fails
+    #[arg(long)]
+    min_entropy: Option<f64>,
+    
+    /// Fail without saving output if the final bits grade below this quality (bare --strict means fair)
+    #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "fair")]
+    strict: Option<Quality>,
+    
+    /// Write a JSON statistics report of the final bits to a file (or stdout with "-" or no value)
+    #[arg(long, num_args = 0..=1, default_missing_value = "-")]
+    report_json: Option<String>,
+    
+    /// Discard collected windows whose Shannon entropy (bits/bit) falls below this value (between 0 and 1)
+    ///
+    /// Collection fails once 100 windows in a row have been discarded.
+    #[arg(long, value_parser = parse_entropy_threshold)]
+    quality_gate: Option<f64>,
+    
+    /// Window size in bits evaluated by --quality-gate (at least 64)
+    #[arg(long, default_value_t = DEFAULT_QUALITY_WINDOW_BITS, value_parser = clap::value_parser!(u64).range(MIN_QUALITY_WINDOW_BITS as u64..).map(|bits| bits as usize))]
+    quality_window: usize,
+    
+    /// Write the ones proportion of each block of raw bits to this CSV file, to plot drift
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["buffer_bits", "low_latency", "stream", "fifo", "seed_bits", "expand"])]
+    bias_csv: Option<String>,
+    
+    /// Raw bits per --bias-csv row (default: about 1000 rows, at least 64 bits each)
+    #[arg(long, requires = "bias_csv", value_parser = clap::value_parser!(u64).range(1..).map(|bits| bits as usize))]
+    bias_block: Option<usize>,
+    
+    /// Save the raw bits to this file as they are collected and resume from it when rerun,
+    /// so a crash or Ctrl-C in a long collection loses at most a few seconds of data
+    ///
+    /// Processing starts only once --num-bits raw bits are saved (or with
+    /// --finalize); the file is deleted after the output is written.
+    #[arg(
+        long, value_name = "PATH",
+        conflicts_with_all = [
+            "stream", "fifo", "seed_bits", "expand", "buffer_bits", "low_latency", "repeat", "tui",
+            "min_entropy", "count_only", "dry_run"
+        ]
+    )]
+    checkpoint: Option<String>,
+    
+    /// Process the bits saved in the --checkpoint file now, even if fewer than --num-bits
+    #[arg(long, default_value_t = false, requires = "checkpoint")]
+    finalize: bool,
+    
+    /// Abort if the source delivers no new bits for this many seconds (for unattended jobs)
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    stall_timeout_s: Option<u64>,
+    
+    /// Cap the raw collection rate (bits per second) by sleeping between reads, for background use
+    #[arg(long, value_name = "BITS_PER_SEC", value_parser = parse_positive_rate)]
+    max_rate: Option<f64>,
+    
+    /// Drop this many initial bits while the sensor settles
+    #[arg(long, default_value_t = 0)]
+    discard_initial: usize,
+    
+    /// Skip the startup health tests that reject a stuck or constant source
+    #[arg(long, default_value_t = false)]
+    no_health_check: bool,
+}
+
+/// Post-processing applied to collected bits by `process_bits`
+struct ProcessOptions<'a> {
+    mix_file: Option<&'a str>,
+    mix_os_rng: bool,
+    extractor: Option<Extractor>,
+    symbol_bits: usize,
+    whitening: Whitening,
+    /// SHAKE256 output length; the input length is preserved when unset
+    output_bits: Option<usize>,
+    /// Size SHAKE256 output to the estimated min-entropy of its input
+    size_to_min_entropy: bool,
+    fold_passes: usize,
+    hash: HashAlgo,
+    profile: Option<Profile>,
+    min_entropy: Option<f64>,
+    output_file: Option<&'a str>,
+    strict: Option<Quality>,
+    feed_kernel: bool,
+    stdout_binary: bool,
+    key: Option<&'a [u8]>,
+    append: bool,
+    encoding: Option<Encoding>,
+    bit_order: BitOrder,
+    print: bool,
+    integers: Option<IntegerRange>,
+    /// Source description for the --with-header output header, when one is written
+    header_source: Option<&'a str>,
+}
+
+impl ProcessOptions<'_> {
+    /// SHAKE256 output length for conditioning `bits`, if fixed
+    fn shake_output_bits(&self, bits: &[u8]) -> Option<usize> {
+        if self.size_to_min_entropy {
+            let min_entropy = utils::most_common_value_estimate(bits, utils::MCV_REPORT_BLOCK_SIZE);
+            Some((bits.len() as f64 * min_entropy).floor() as usize)
+        } else {
+            self.output_bits
+        }
+    }
+}
+
+impl CollectArgs {
+    fn process_options(&self) -> ProcessOptions<'_> {
+        let (extractor, whitening) = match self.profile {
+            Some(Profile::Raw) => (None, Whitening::None),
+            Some(Profile::Balanced) => (Some(Extractor::Classic), Whitening::None),
+            Some(Profile::Paranoid) => (Some(Extractor::Classic), Whitening::Shake),
+            None => (
+                self.extractor.or(self.apply_debiasing.then_some(Extractor::Classic)),
+                self.whitening.unwrap_or(
+                    if self.output_bits.is_some() { Whitening::Shake }
+                    else if self.apply_hashing || self.key.is_some() { Whitening::Sha3 }
+                    else { Whitening::None }
+                ),
+            ),
+        };
+        
+        ProcessOptions {
+            mix_file: self.mix_file.as_deref(),
+            mix_os_rng: self.mix_os_rng,
+            extractor,
+            symbol_bits: self.symbol_bits,
+            whitening,
+            output_bits: self.output_bits,
+            size_to_min_entropy: self.profile == Some(Profile::Paranoid),
+            fold_passes: self.fold_passes,
+            hash: self.hash,
+            profile: self.profile,
+            min_entropy: self.min_entropy,
+            output_file: self.output_file.as_deref(),
+            strict: self.strict,
+            feed_kernel: self.feed_kernel,
+            stdout_binary: self.stdout_binary,
+            key: self.key.as_deref(),
+            append: self.append,
+            encoding: self.output_encoding(),
+            bit_order: self.bit_order,
+            print: self.print,
+            integers: self.integers,
+            header_source: None,
+        }
+    }
+    
+    /// Encoding chosen by --output-format or --encoding, if any
+    fn output_encoding(&self) -> Option<Encoding> {
+        self.output_format.map(|format| format.encoding()).or(self.encoding)
+    }
+}
+
+/// Post-processing preset for users who do not want to pick individual flags
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Profile {
+    /// No post-processing
+    Raw,
+    /// Von Neumann debiasing
+    Balanced,
+    /// Von Neumann debiasing, then SHAKE256 sized to the estimated min-entropy
+    Paranoid,
+}
+
+impl Profile {
+    fn description(&self) -> &'static str {
+        match self {
+            Profile::Raw => "no post-processing",
+            Profile::Balanced => "Von Neumann debiasing",
+            Profile::Paranoid => "Von Neumann debiasing, then SHAKE256 sized to the estimated min-entropy",
+        }
+    }
+}
+
+/// Debiasing algorithm applied to the raw bits
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Extractor {
+    /// Classic Von Neumann pairing (~25% yield on unbiased input)
+    Classic,
+    /// Peres' iterated Von Neumann (recycles discarded pairs)
+    Peres,
+    /// Seeded Toeplitz hashing sized from the MCV min-entropy estimate (information-theoretic)
+    Toeplitz,
+    /// Elias' multi-bit generalization of Von Neumann (near-optimal yield, see --symbol-bits)
+    Elias,
+}
+
+impl Extractor {
+    fn apply(self, bits: &[u8], symbol_bits: usize) -> Vec<u8> {
+        match self {
+            Extractor::Classic => utils::von_neumann_debias(bits),
+            Extractor::Elias => utils::elias_extract(bits, symbol_bits),
+            Extractor::Peres => utils::von_neumann_debias_peres(bits),
+            Extractor::Toeplitz => {
+                let min_entropy = utils::most_common_value_estimate(bits, utils::MCV_REPORT_BLOCK_SIZE);
+                utils::toeplitz_extract(bits, min_entropy)
+            },
+        }
+    }
+    
+    /// Input bits the extractor consumes together; chunked processing carries any remainder
+    ///
+    /// Toeplitz has no block structure but pays its security margin on every
+    /// call, so chunks are gathered into multiples of `TOEPLITZ_MIN_BLOCK_BITS`.
+    fn block_bits(self, symbol_bits: usize) -> usize {
+        match self {
+            Extractor::Classic | Extractor::Peres => 2,
+            Extractor::Elias => symbol_bits.clamp(2, utils::ELIAS_MAX_SYMBOL_BITS),
+            Extractor::Toeplitz => TOEPLITZ_MIN_BLOCK_BITS,
+        }
+    }
+}
+
+/// Conditioning applied after debiasing
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Whitening {
+    None,
+    /// XOR the two halves of the stream together (cheap, not cryptographic)
+    XorFold,
+    /// Hash with --hash: 256 bits for SHA3-256, 512 for SHA3-512, the input length for BLAKE3
+    Sha3,
+    /// SHAKE256 to --output-bits bits (default: same length as the input)
+    Shake,
+}
+
+impl Whitening {
+    /// With a `key`, the hashes become HMAC-SHA3-256 of the same output length
+    fn apply(self, bits: &[u8], output_bits: Option<usize>, fold_passes: usize, hash: HashAlgo, key: Option<&[u8]>) -> Vec<u8> {
+        match (self, key) {
+            (Whitening::None, _) => bits.to_vec(),
+            (Whitening::XorFold, _) => utils::xor_fold(bits, fold_passes),
+            (Whitening::Sha3, None) => utils::hash_randomness_with(bits, hash),
+            (Whitening::Sha3, Some(key)) => {
+                let digest_bits = match hash {
+                    HashAlgo::Sha3_256 => 256,
+                    HashAlgo::Sha3_512 => 512,
+                    HashAlgo::Blake3 => bits.len(),
+                };
+                utils::hmac_condition(bits, key, digest_bits)
+            },
+            (Whitening::Shake, None) => utils::hash_randomness_xof(bits, output_bits.unwrap_or(bits.len())),
+            (Whitening::Shake, Some(key)) => utils::hmac_condition(bits, key, output_bits.unwrap_or(bits.len())),
+        }
+    }
+}
+
+/// Serialization of the final bits written to --output-file
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Encoding {
+    /// Packed bytes (trailing bits are zero-padded)
+    Binary,
+    /// Lowercase hex of the packed bytes
+    Hex,
+    /// Standard base64 of the packed bytes
+    Base64,
+    /// One ASCII '0' or '1' per bit
+    Text,
+    /// MSB-first packed bytes with trailing bits dropped (selected by --output-format binary-sts)
+    #[value(skip)]
+    BinarySts,
+}
+
+/// Input layout of the NIST Statistical Test Suite (`assess`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// One line of ASCII '0'/'1' characters (STS input mode 0)
+    AsciiSts,
+    /// Bytes read most significant bit first, no padding (STS input mode 1)
+    BinarySts,
+}
+
+impl OutputFormat {
+    fn encoding(&self) -> Encoding {
+        match self {
+            OutputFormat::AsciiSts => Encoding::Text,
+            OutputFormat::BinarySts => Encoding::BinarySts,
+        }
+    }
+}
+
+fn main() {
+    let config_warnings = load_config_defaults();
+    let matches = with_env_defaults(Cli::command()).get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    init_logging(cli.verbose, cli.quiet);
+    for warning in config_warnings {
+        log::warn!("{warning}");
+    }
+    
+    if let Err(e) = execute(&cli) {
+        log::error!("{e:#}");
+        std::process::exit(1);
+    }
+}
+
+/// Environment variable name for an option, e.g. `num_bits` -> `QRNG_NUM_BITS`
+fn env_var_name(id: &str) -> String {
+    format!("{ENV_PREFIX}{}", id.replace('-', "_").to_uppercase())
+}
+
+/// Let every long option of every subcommand also be set through its `QRNG_*` variable
+fn with_env_defaults(command: clap::Command) -> clap::Command {
+    let subcommands: Vec<String> = command.get_subcommands().map(|sub| sub.get_name().to_string()).collect();
+    let command = command.mut_args(|arg| {
+        if arg.get_long().is_none() || matches!(arg.get_action(), clap::ArgAction::Count) {
+            return arg;
+        }
+        let name = env_var_name(arg.get_id().as_str());
+        arg.env(name)
+    });
+    subcommands.into_iter().fold(command, |command, name| command.mut_subcommand(name, with_env_defaults))
+}
+
+/// Long option ids of the command and all its subcommands
+fn option_ids(command: &clap::Command, ids: &mut Vec<String>) {
+    ids.extend(command.get_arguments().filter(|arg| arg.get_long().is_some()).map(|arg| arg.get_id().to_string()));
+    for sub in command.get_subcommands() {
+        option_ids(sub, ids);
+    }
+}
+
+/// Path of the config file: $XDG_CONFIG_HOME/qrng/config.toml, else ~/.config/qrng/config.toml
+fn config_path() -> Option<std::path::PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(std::path::PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| std::path::Path::new(&home).join(".config")))?;
+    Some(base.join("qrng").join("config.toml"))
+}
+
+/// Export the keys of the config file as `QRNG_*` variables that are not already set
+///
+/// Precedence is therefore command-line flag, then environment, then config
+/// file, then built-in default.
+///
+/// # Returns
+/// Problems with the file, to be logged once logging is set up
+fn load_config_defaults() -> Vec<String> {
+    let Some(path) = config_path() else {
+        return Vec::new();
+    };
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Vec::new(),
+        Err(e) => return vec![format!("Ignoring config file {}: {e}", path.display())],
+    };
+    let table = match contents.parse::<toml::Table>() {
+        Ok(table) => table,
+        Err(e) => return vec![format!("Ignoring invalid config file {}: {e}", path.display())],
+    };
+    
+    let mut known = Vec::new();
+    option_ids(&Cli::command(), &mut known);
+    
+    let mut warnings = Vec::new();
+    for (key, value) in table {
+        let id = key.replace('-', "_");
+        if !known.contains(&id) {
+            warnings.push(format!("Unknown option '{key}' in {}", path.display()));
+            continue;
+        }
+        let value = match value {
+            toml::Value::String(text) => text,
+            toml::Value::Array(items) => items.iter()
+                .map(|item| item.as_str().map(str::to_string).unwrap_or_else(|| item.to_string()))
+                .collect::<Vec<_>>()
+                .join(","),
+            toml::Value::Table(_) => {
+                warnings.push(format!("Option '{key}' in {} must be a single value", path.display()));
+                continue;
+            }
+            other => other.to_string(),
+        };
+        let name = env_var_name(&id);
+        if std::env::var_os(&name).is_none() {
+            std::env::set_var(name, value);
+        }
+    }
+    warnings
+}
+
+/// Send diagnostics to stderr with timestamps; results stay on stdout
+fn init_logging(verbose: u8, quiet: bool) {
+    let level = match (quiet, verbose) {
+        (true, _) => log::LevelFilter::Warn,
+        (false, 0) => log::LevelFilter::Info,
+        (false, 1) => log::LevelFilter::Debug,
+        (false, _) => log::LevelFilter::Trace,
+    };
+    env_logger::Builder::new()
+        .filter_level(level)
+        .parse_default_env()
+        .init();
+}
+
+fn execute(cli: &Cli) -> anyhow::Result<()> {
+    quantum_rng::progress::set_quiet(cli.quiet);
+    
+    // First Ctrl-C stops collection and keeps what was gathered; a second one exits immediately
+    let interrupted = Arc::new(AtomicBool::new(false));
+    let handler_flag = interrupted.clone();
+    ctrlc::set_handler(move || {
+        if handler_flag.swap(true, Ordering::Relaxed) {
+            std::process::exit(130);
+        }
+        log::warn!("Interrupted: finishing with the bits collected so far (press Ctrl-C again to abort)");
+    })?;
+    let cancel = interrupted.as_ref();
 
     match &cli.command {
-        Commands::Webcam { num_bits, output_file, apply_debiasing, apply_hashing } => {
-            println!("Generating {num_bits} random bits using webcam quantum noise...");
-            let start = Instant::now();
-            let mut bits = webcam::webcam_qrng(*num_bits)?;
-            let duration = start.elapsed();
-            println!("Collection completed in {:.2?}", duration);
-            
-            process_bits(&mut bits, *apply_debiasing, *apply_hashing, output_file.as_deref())?;
+        Commands::Webcam {
+            camera_index, width, height, bit_planes, mix_timing, webcam_diff, max_frames, frame_delay_ms, webcam_channels, webcam_extract, frame_hash_bits,
+            pixel_stride, frame_xor, args
+        } => {
+            if *webcam_extract == WebcamExtract::FrameHash && (*webcam_diff || *bit_planes != 1 || *pixel_stride != 1) {
+                return Err(anyhow::anyhow!(
+                    "--webcam-diff, --bit-planes and --pixel-stride select pixel bits and do not apply to --webcam-extract frame-hash"
+                ));
+            }
+            let config = WebcamConfig {
+                camera_index: *camera_index,
+                width: *width,
+                height: *height,
+                bit_mask: *bit_planes,
+                mix_timing: *mix_timing,
+                spatial_diff: *webcam_diff,
+                max_frames: *max_frames,
+                frame_delay: Duration::from_millis(*frame_delay_ms),
+                channels: *webcam_channels,
+                extract: *webcam_extract,
+                frame_hash_bits: *frame_hash_bits,
+                pixel_stride: *pixel_stride,
+                frame_xor: *frame_xor,
+            };
+            run(&mut WebcamSource::with_config(config), &format!("webcam {camera_index} quantum noise"), args, cancel)?;
         },
         
-        Commands::Audio { num_bits, output_file, apply_debiasing, apply_hashing } => {
-            println!("Generating {num_bits} random bits using microphone quantum noise...");
-            let start = Instant::now();
-            let mut bits = audio::audio_qrng(*num_bits)?;
-            let duration = start.elapsed();
-            println!("Collection completed in {:.2?}", duration);
-            
-            process_bits(&mut bits, *apply_debiasing, *apply_hashing, output_file.as_deref())?;
+        Commands::Audio { audio_bits, decimate, audio_device, args } => {
+            let config = AudioConfig {
+                bits_per_sample: *audio_bits,
+                decimation: *decimate as usize,
+                device: audio_device.clone(),
+            };
+            let description = match audio_device {
+                Some(name) => format!("microphone '{name}' quantum noise"),
+                None => "microphone quantum noise".to_string(),
+            };
+            run(&mut AudioSource::with_config(config), &description, args, cancel)?;
         },
         
-        Commands::Serial { port, num_bits, output_file, apply_debiasing, apply_hashing } => {
-            println!("Generating {num_bits} random bits using Arduino on port {port}...");
-            let start = Instant::now();
-            let mut bits = serial::serial_qrng(port, *num_bits)?;
-            let duration = start.elapsed();
-            println!("Collection completed in {:.2?}", duration);
-            
-            process_bits(&mut bits, *apply_debiasing, *apply_hashing, output_file.as_deref())?;
+        Commands::Serial { port, baud, protocol, max_retries, read_timeout_ms, collection_timeout_s, args } => {
+            let protocol = protocol.protocol()?;
+            let config = SerialConfig {
+                port_name: port.clone(),
+                baud_rate: *baud,
+                format: protocol.format,
+                max_retries: *max_retries,
+                delimiter: protocol.delimiter,
+                read_timeout: Duration::from_millis(*read_timeout_ms),
+                collection_timeout: collection_timeout_s.map(Duration::from_secs),
+                channels: protocol.channels,
+                combine: protocol.combine,
+                sample_bytes: protocol.sample_bytes,
+                endian: protocol.endian,
+            };
+            run(&mut SerialSource::with_config(config), &format!("Arduino on port {port}"), args, cancel)?;
+        },
+        
+        Commands::Tcp { addr, protocol, max_retries, read_timeout_ms, collection_timeout_s, args } => {
+            let config = TcpConfig {
+                addr: addr.clone(),
+                protocol: protocol.protocol()?,
+                max_retries: *max_retries,
+                read_timeout: Duration::from_millis(*read_timeout_ms),
+                collection_timeout: collection_timeout_s.map(Duration::from_secs),
+            };
+            run(&mut TcpSource::with_config(config), &format!("noise source at {addr}"), args, cancel)?;
+        },
+        
+        Commands::Multi { port, args } => {
+            let verbose = log::log_enabled!(log::Level::Trace);
+            run(&mut MultiSource::new(port.as_deref(), verbose), "pooled entropy sources", args, cancel)?;
+        },
+        
+        Commands::Auto { port, args } => {
+            let (mut source, description) = auto_source(port.as_deref())?;
+            run(source.as_mut(), &description, args, cancel)?;
+        },
+        
+        Commands::File { input, args } => {
+            // Parallel batches replay consecutive, non-overlapping stretches of the file
+            let batch_bytes = (args.num_bits + args.discard_initial).div_ceil(8) as u64;
+            let open = |batch: usize| -> Box<dyn QrngSource + Send> { Box::new(FileSource::with_offset(input, batch as u64 * batch_bytes)) };
+            run_independent(open, &format!("bytes from {input}"), args, cancel)?;
+        },
+        
+        Commands::Mock { seed, args } => {
+            let args = CollectArgs { generator: Some(MOCK_OUTPUT_LABEL), ..args.clone() };
+            // Parallel batches use consecutive seeds
+            let open = |batch: usize| -> Box<dyn QrngSource + Send> { Box::new(MockSource::new(seed.wrapping_add(batch as u64))) };
+            run_independent(open, &format!("{MOCK_OUTPUT_LABEL} (seed {seed})"), &args, cancel)?;
+        },
+        
+        Commands::Verify { input, bit_order } => {
+            if !verify(input, *bit_order)? {
+                std::process::exit(1);
+            }
+        },
+        
+        Commands::Compare { a, b, bit_order, allow_length_mismatch } => compare(a, b, *bit_order, *allow_length_mismatch)?,
+        
+        Commands::ListDevices => list_devices(),
+        
+        Commands::Benchmark { source, port, duration, json } => {
+            let mut qrng = source.build(port.as_deref())?;
+            benchmark(qrng.as_mut(), *source, Duration::from_secs(*duration), *json, cancel)?;
         },
     }
 
     Ok(())
 }
 
-fn process_bits(bits: &mut Vec<u8>, apply_debiasing: bool, apply_hashing: bool, output_file: Option<&str>) -> anyhow::Result<()> {
-    let original_len = bits.len();
-    
-    // Calculate entropy statistics
-    let mean = bits.iter().map(|&x| x as f64).sum::<f64>() / bits.len() as f64;
-    println!("Generated {original_len} bits");
-    println!("Raw bit mean: {mean:.4} (ideal: 0.5)");
-    
-    let mut result_bits = bits.clone();
-    
-    // Apply Von Neumann debiasing if requested
-    if apply_debiasing {
-        result_bits = utils::von_neumann_debias(&result_bits);
-        let debiased_mean = result_bits.iter().map(|&x| x as f64).sum::<f64>() / result_bits.len().max(1) as f64;
-        println!("After debiasing: {} bits", result_bits.len());
-        println!("Debiased mean: {:.4} (ideal: 0.5)", debiased_mean);
+/// Open the first available source of webcam, microphone and serial port
+///
+/// Each candidate reads one chunk (which is discarded) to prove it works. A
+/// missing device moves on to the next candidate; any other failure, such as
+/// a camera that opens but errors, is returned.
+fn auto_source(port: Option<&str>) -> anyhow::Result<(Box<dyn QrngSource>, String)> {
+    let mut candidates: Vec<(Box<dyn QrngSource>, String)> = vec![
+        (Box::new(WebcamSource::new()), "webcam 0 quantum noise".to_string()),
+        (Box::new(AudioSource::new()), "microphone quantum noise".to_string()),
+    ];
+    if let Some(port) = port {
+        candidates.push((Box::new(SerialSource::new(port)), format!("Arduino on port {port}")));
     }
     
-    // Apply SHA-3 hashing if requested
-    if apply_hashing {
-        result_bits = utils::hash_randomness(&result_bits);
-        println!("After cryptographic hashing: {} bits", result_bits.len());
+    let mut missing = Vec::new();
+    for (mut source, description) in candidates {
+        match source.read_chunk() {
+            Ok(_) => {
+                log::info!("Auto-selected {description}");
+                return Ok((source, description));
+            },
+            Err(e) if e.is_no_device() => {
+                log::info!("Skipping {description}: {e}");
+                missing.push(description);
+            },
+            Err(e) => return Err(e.into()),
+        }
     }
     
-    if let Some(filename) = output_file {
-        let mut file = File::create(filename)?;
-        
-        // Write as binary data if bits are multiples of 8
-        if result_bits.len() % 8 == 0 {
-            let bytes = utils::bits_to_bytes(&result_bits);
-            file.write_all(&bytes)?;
-            println!("Random bits saved to {filename} ({} bytes)", bytes.len());
-        } else {
-            for bit in &result_bits {
-                write!(file, "{}", bit)?;
-            }
-            println!("Random bits saved to {filename} as text ({} bits)", result_bits.len());
-        }
+    let hint = if port.is_none() { "; pass --port to also try an Arduino" } else { "" };
+    Err(anyhow::anyhow!("No entropy source is available (tried {}){hint}", missing.join(", ")))
+}
+
+/// Parse a rate limit, which must be a positive number
+fn parse_positive_rate(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(rate) if rate > 0.0 && rate.is_finite() => Ok(rate),
+        _ => Err(format!("expected a positive number of bits per second, got '{value}'")),
+    }
+}
+
+/// Parse a --quality-gate threshold, which must lie strictly between 0 and 1 bits per bit
+fn parse_entropy_threshold(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(threshold) if threshold > 0.0 && threshold < 1.0 => Ok(threshold),
+        _ => Err(format!("expected an entropy strictly between 0 and 1 bits per bit, got '{value}'")),
+    }
+}
+
+/// Inclusive range and number of the integers requested by --integers
+#[derive(Clone, Copy, Debug)]
+struct IntegerRange {
+    min: i64,
+    max: i64,
+    count: usize,
+}
+
+/// Parse an integer request "MIN:MAX:COUNT" such as "1:6:100"
+fn parse_integer_range(value: &str) -> Result<IntegerRange, String> {
+    let parts: Vec<&str> = value.split(':').map(str::trim).collect();
+    let [min, max, count] = parts[..] else {
+        return Err(format!("expected MIN:MAX:COUNT, got '{value}'"));
+    };
+    let min: i64 = min.parse().map_err(|_| format!("invalid minimum '{min}'"))?;
+    let max: i64 = max.parse().map_err(|_| format!("invalid maximum '{max}'"))?;
+    let count: usize = count.parse().map_err(|_| format!("invalid count '{count}'"))?;
+    if max < min {
+        return Err(format!("maximum {max} is below minimum {min}"));
+    }
+    
+    Ok(IntegerRange { min, max, count })
+}
+
+/// Parse a hex string such as "a1b2" (optionally "0x"-prefixed) into key bytes
+fn parse_hex_key(value: &str) -> Result<Vec<u8>, String> {
+    let hex = value.strip_prefix("0x").unwrap_or(value);
+    if !hex.is_ascii() {
+        return Err(format!("invalid hex digits in '{value}'"));
+    }
+    if hex.is_empty() || hex.len() % 2 != 0 {
+        return Err(format!("expected an even number of hex digits, got '{value}'"));
+    }
+    
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| format!("invalid hex digits in '{value}'")))
+        .collect()
+}
+
+/// Parse a bit-plane selection like "0-2" or "0,3" into a bit mask
+fn parse_bit_planes(value: &str) -> Result<u8, String> {
+    let mut mask = 0u8;
+    
+    for part in value.split(',') {
+        let (start, end) = match part.split_once('-') {
+            Some((start, end)) => (start.trim(), end.trim()),
+            None => (part.trim(), part.trim()),
+        };
+        let start: u8 = start.parse().map_err(|_| format!("invalid bit plane '{start}'"))?;
+        let end: u8 = end.parse().map_err(|_| format!("invalid bit plane '{end}'"))?;
+        
+        if start > end || end > 7 {
+            return Err(format!("bit planes must be in 0-7, got '{part}'"));
+        }
+        for plane in start..=end {
+            mask |= 1 << plane;
+        }
+    }
+    
+    Ok(mask)
+}
+
+fn run(source: &mut dyn QrngSource, description: &str, args: &CollectArgs, cancel: &AtomicBool) -> anyhow::Result<()> {
+    if args.stdout_binary && args.report_json.as_deref() == Some("-") {
+        return Err(anyhow::anyhow!("--report-json needs a file path with --stdout-binary, which uses stdout for the bits"));
+    }
+    
+    if args.dry_run {
+        print_estimate(source, description, args);
+        return Ok(());
+    }
+    
+    if args.count_only {
+        return count_yield(source, description, args, cancel);
+    }
+    
+    if let Some(path) = &args.raw_capture {
+        let file = std::fs::File::create(path)
+            .map_err(|e| anyhow::anyhow!("Failed to create raw capture file {path}: {e}"))?;
+        if source.set_raw_capture(Box::new(std::io::BufWriter::new(file))) {
+            log::info!("Saving raw sensor data to {path}");
+        } else {
+            log::warn!("{description} has no raw sensor data; --raw-capture {path} will stay empty");
+        }
+    }
+    
+    let Some(repeat) = args.repeat.filter(|&repeat| repeat > 1) else {
+        return run_batch(source, description, args, cancel);
+    };
+    
+    for batch in 0..repeat {
+        if cancel.load(Ordering::Relaxed) {
+            log::info!("Stopped after {batch} of {repeat} batches");
+            break;
+        }
+        
+        log::debug!("Batch {}/{repeat}", batch + 1);
+        run_batch(source, description, &batch_args(args, batch, repeat), cancel)?;
+    }
+    
+    Ok(())
+}
+
+/// Run a source that can be opened once per batch, generating --repeat batches in parallel
+///
+/// `open` returns the independent source of a batch (batch 0 is the source
+/// a single run would use). Only file and mock sources qualify: they are
+/// CPU-bound and share no device, so batches spread over the thread pool.
+/// Hardware sources go through `run`, which keeps batches serial. Having no
+/// sensor data, these sources reject --raw-capture.
+fn run_independent<F>(open: F, description: &str, args: &CollectArgs, cancel: &AtomicBool) -> anyhow::Result<()>
+where
+    F: Fn(usize) -> Box<dyn QrngSource + Send> + Sync,
+{
+    if args.raw_capture.is_some() {
+        return Err(anyhow::anyhow!("--raw-capture needs a hardware source; {description} has no raw sensor data"));
+    }
+    
+    let repeat = args.repeat.unwrap_or(1);
+    if repeat <= 1 || args.dry_run || args.tui {
+        return run(open(0).as_mut(), description, args, cancel);
+    }
+    
+    log::info!("Generating {repeat} batches on {} threads...", rayon::current_num_threads());
+    let start = Instant::now();
+    let results: Vec<anyhow::Result<Option<Duration>>> = (0..repeat)
+        .into_par_iter()
+        .map(|batch| {
+            if cancel.load(Ordering::Relaxed) {
+                return Ok(None);
+            }
+            let batch_start = Instant::now();
+            run_batch(open(batch).as_mut(), description, &batch_args(args, batch, repeat), cancel)?;
+            Ok(Some(batch_start.elapsed()))
+        })
+        .collect();
+    let wall = start.elapsed();
+    
+    let mut timings = Vec::with_capacity(repeat);
+    for result in results {
+        timings.extend(result?);
+    }
+    if timings.len() < repeat {
+        log::info!("Stopped after {} of {repeat} batches", timings.len());
+    }
+    if let Some(longest) = timings.iter().max() {
+        let total: Duration = timings.iter().sum();
+        log::info!(
+            "{} batches in {wall:.2?} wall time: {:.2?} per batch on average (longest {longest:.2?}), {:.1}x faster than one at a time",
+            timings.len(),
+            total / timings.len() as u32,
+            total.as_secs_f64() / wall.as_secs_f64().max(f64::EPSILON),
+        );
+    }
+    Ok(())
+}
+
+/// Settings of batch number `batch` of `repeat`: numbered output files, warmup only if needed
+fn batch_args(args: &CollectArgs, batch: usize, repeat: usize) -> CollectArgs {
+    let output_file = args.output_file.as_deref().expect("--repeat requires --output-file");
+    let mut batch_args = args.clone();
+    batch_args.output_file = Some(numbered_path(output_file, batch, repeat));
+    batch_args.report_json = args.report_json.as_deref()
+        .map(|target| if target == "-" { target.to_string() } else { numbered_path(target, batch, repeat) });
+    if batch > 0 && !args.rewarm {
+        batch_args.discard_initial = 0;
+    }
+    batch_args
+}
+
+/// Insert a zero-padded batch number before the extension: "batch.bin" -> "batch_007.bin"
+fn numbered_path(path: &str, batch: usize, batches: usize) -> String {
+    let width = (batches - 1).to_string().len().max(3);
+    let path = std::path::Path::new(path);
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+    let name = match path.extension() {
+        Some(extension) => format!("{stem}_{batch:0width$}.{}", extension.to_string_lossy()),
+        None => format!("{stem}_{batch:0width$}"),
+    };
+    path.with_file_name(name).to_string_lossy().into_owned()
+}
+
+/// Watch the source for stalls if requested and run one batch
+fn run_batch(source: &mut dyn QrngSource, description: &str, args: &CollectArgs, cancel: &AtomicBool) -> anyhow::Result<()> {
+    let Some(timeout) = args.stall_timeout_s.map(Duration::from_secs) else {
+        return run_warmed(source, description, args, cancel);
+    };
+    
+    let mut watchdog = StallWatchdog::new(source, description, timeout);
+    spawn_stall_monitor(watchdog.state(), description.to_string(), timeout);
+    run_warmed(&mut watchdog, description, args, cancel)
+}
+
+/// Abort the process if a single read stays blocked well past the stall timeout
+///
+/// The watchdog reports stalls itself whenever a read returns; this thread
+/// covers a read that never does, such as a camera hung inside its driver.
+/// The stall clock only runs inside reads, so the processing and writing that
+/// follow collection cannot trip it. It exits once the watchdog is dropped.
+fn spawn_stall_monitor(state: Arc<StallState>, description: String, timeout: Duration) {
+    std::thread::spawn(move || {
+        while !state.finished() {
+            std::thread::sleep(STALL_MONITOR_INTERVAL);
+            if !state.finished() && state.stalled_for() > timeout * 2 {
+                log::error!(
+                    "No bits from {description} for {:.0?} ({} bits were gathered); the device is blocked, aborting",
+                    state.stalled_for(), state.bits()
+                );
+                std::process::exit(1);
+            }
+        }
+    });
+}
+
+/// Warm up the source, apply the rate limit and quality gate if requested and run one collection
+fn run_warmed(source: &mut dyn QrngSource, description: &str, args: &CollectArgs, cancel: &AtomicBool) -> anyhow::Result<()> {
+    if args.discard_initial > 0 {
+        log::info!("Discarding the first {} bits while {description} warms up...", args.discard_initial);
+        source.begin_collection();
+        source.discard_bits(args.discard_initial, cancel)?;
+    }
+    
+    let Some(max_rate) = args.max_rate else {
+        return run_gated(source, description, args, cancel);
+    };
+    
+    let mut limiter = RateLimiter::new(source, max_rate);
+    let result = run_gated(&mut limiter, description, args, cancel);
+    if let Some(achieved) = limiter.achieved_rate() {
+        let throttled = limiter.throttled_time();
+        let limited_by = if throttled.is_zero() { "device-limited".to_string() } else { format!("throttled for {throttled:.1?}") };
+        log::info!("Achieved rate: {achieved:.1} bits/sec (limit {max_rate:.1}, {limited_by})");
+    }
+    result
+}
+
+/// Run one collection, behind the quality gate if requested
+fn run_gated(source: &mut dyn QrngSource, description: &str, args: &CollectArgs, cancel: &AtomicBool) -> anyhow::Result<()> {
+    let Some(min_entropy) = args.quality_gate else {
+        return run_collection(source, description, args, cancel);
+    };
+    
+    let mut gate = QualityGate::with_window(source, min_entropy, args.quality_window);
+    let result = run_collection(&mut gate, description, args, cancel);
+    log::info!(
+        "Quality gate: {} windows passed, {} rejected (minimum entropy {min_entropy:.4})",
+        gate.passed_windows(), gate.rejected_windows()
+    );
+    result
+}
+
+/// Report how long --num-bits (plus any --discard-initial warmup) should take
+fn print_estimate(source: &dyn QrngSource, description: &str, args: &CollectArgs) {
+    let Some(rate) = source.estimated_rate_bits_per_sec().filter(|&rate| rate > 0.0) else {
+        println!("Dry run: no rate estimate is available for {description}");
+        return;
+    };
+    
+    let raw_bits = args.num_bits + args.discard_initial;
+    let secs = raw_bits as f64 / rate;
+    println!("Dry run: {description} delivers about {rate:.0} raw bits/s");
+    println!("Collecting {raw_bits} raw bits would take about {}", format_duration(secs));
+    if args.extractor.is_some() || args.apply_debiasing || args.profile.is_some_and(|profile| profile != Profile::Raw) {
+        println!("Note: debiasing keeps roughly a quarter of the raw bits");
+    }
+}
+
+/// Collect for the --count-only window and report how many bits survive debiasing
+///
+/// Uses the --extractor (or profile) chosen, von Neumann otherwise, so
+/// extraction settings can be compared by their yield.
+fn count_yield(source: &mut dyn QrngSource, description: &str, args: &CollectArgs, cancel: &AtomicBool) -> anyhow::Result<()> {
+    if args.discard_initial > 0 {
+        log::info!("Discarding the first {} bits while {description} warms up...", args.discard_initial);
+        source.begin_collection();
+        source.discard_bits(args.discard_initial, cancel)?;
+    }
+    
+    let window = Duration::from_secs(args.duration);
+    source.begin_collection();
+    log::info!("Counting the yield of {description} over {}...", format_duration(window.as_secs_f64()));
+    let units_before = source.units_read();
+    let start = Instant::now();
+    let raw = source.collect_for(window, cancel)?;
+    let elapsed = start.elapsed().as_secs_f64().max(f64::EPSILON);
+    
+    let options = args.process_options();
+    let extractor = options.extractor.unwrap_or(Extractor::Classic);
+    let debiased = extractor.apply(&raw, options.symbol_bits);
+    
+    println!("Yield over {elapsed:.1}s:");
+    println!("  Raw bits:        {} ({:.1} bits/sec)", raw.len(), raw.len() as f64 / elapsed);
+    println!(
+        "  Debiased bits:   {} ({:.1} bits/sec, {:.1}% of raw, {} extractor)",
+        debiased.len(),
+        debiased.len() as f64 / elapsed,
+        debiased.len() as f64 / raw.len().max(1) as f64 * 100.0,
+        format!("{extractor:?}").to_lowercase(),
+    );
+    if let Some(units) = units_since(source, units_before).filter(|units| units.count > 0) {
+        println!(
+            "  Bits per {}:  {:.2} raw, {:.2} debiased",
+            units.unit, raw.len() as f64 / units.count as f64, debiased.len() as f64 / units.count as f64
+        );
+    }
+    println!("  Raw min-entropy: {:.4} bits/bit", utils::estimate_min_entropy(&raw));
+    Ok(())
+}
+
+/// Format a duration in seconds as e.g. "2h 5m 13s" or "4.2s"
+fn format_duration(secs: f64) -> String {
+    if secs < 60.0 {
+        return format!("{secs:.1}s");
+    }
+    
+    let total = secs.round() as u64;
+    let (hours, minutes, seconds) = (total / 3600, total % 3600 / 60, total % 60);
+    if hours > 0 {
+        format!("{hours}h {minutes}m {seconds}s")
+    } else {
+        format!("{minutes}m {seconds}s")
+    }
+}
+
+fn run_collection(source: &mut dyn QrngSource, description: &str, args: &CollectArgs, cancel: &AtomicBool) -> anyhow::Result<()> {
+    let health_check = !args.no_health_check;
+    source.begin_collection();
+    
+    if args.stream {
+        log::info!("Streaming random bytes using {description} to stdout...");
+        return stream_bits(source, &mut std::io::stdout().lock(), None, description, health_check, cancel);
+    }
+    
+    if let Some(path) = args.output_file.as_deref().filter(|path| args.fifo || is_fifo(path)) {
+        log::info!("Waiting for a reader on {path}...");
+        let mut fifo = OpenOptions::new().write(true).open(path)?;
+        log::info!("Streaming {} random bits using {description} to {path}...", args.num_bits);
+        return stream_bits(source, &mut fifo, Some(args.num_bits), description, health_check, cancel);
+    }
+    
+    if let Some(seed_bits) = args.seed_bits {
+        let seed = collect_seed(source, seed_bits, description, health_check, cancel)?;
+        println!("{}", seed_hex(&seed));
+        return Ok(());
+    }
+    
+    if let Some(expand_bits) = args.expand {
+        let seed = collect_seed(source, EXPAND_SEED_BITS, description, health_check, cancel)?;
+        let bits = utils::csprng_expand(&utils::bits_to_bytes(&seed, BitOrder::default()), expand_bits);
+        log::info!("Expanded a {EXPAND_SEED_BITS}-bit seed to {} bits ({EXPANDED_OUTPUT_LABEL})", bits.len());
+        
+        if let Some(filename) = args.output_file.as_deref() {
+            let encoding = args.output_encoding().unwrap_or(
+                if bits.len() % 8 == 0 { Encoding::Binary } else { Encoding::Text }
+            );
+            write_output(filename, &bits, encoding, args.bit_order, args.append)?;
+        }
+        if let Some(target) = &args.report_json {
+            write_report_json(&bits, target, Some(EXPANDED_OUTPUT_LABEL), None)?;
+        }
+        return Ok(());
+    }
+    
+    if let Some(buffer_bits) = args.buffer_bits.or(args.low_latency.then_some(LOW_LATENCY_BLOCK_BITS)) {
+        return process_chunked(source, description, args, buffer_bits.max(8), cancel);
+    }
+    
+    let num_bits = args.num_bits;
+    log::info!("Generating {num_bits} random bits using {description}...");
+    let start = Instant::now();
+    let units_before = source.units_read();
+    
+    let mut bits = if let Some(path) = args.checkpoint.as_deref() {
+        match collect_checkpointed(source, path, description, args, cancel)? {
+            Some(bits) => bits,
+            None => return Ok(()),
+        }
+    } else if args.tui {
+        collect_tui(source, num_bits, description, health_check, cancel)?
+    } else {
+        collect(source, num_bits, description, health_check, cancel)?
+    };
+    
+    // Re-collect while the raw entropy is below the requested floor
+    if let Some(min_entropy) = args.min_entropy {
+        for attempt in 2..=MIN_ENTROPY_ATTEMPTS {
+            let entropy = utils::estimate_entropy(&bits);
+            if entropy >= min_entropy || cancel.load(Ordering::Relaxed) {
+                break;
+            }
+            log::info!("Raw entropy {entropy:.4} is below the minimum {min_entropy:.4}, re-collecting (attempt {attempt}/{MIN_ENTROPY_ATTEMPTS})...");
+            bits = collect(source, num_bits, description, health_check, cancel)?;
+        }
+    }
+    
+    let duration = start.elapsed();
+    log::info!("Collection completed in {:.2?}", duration);
+    
+    if bits.is_empty() {
+        log::info!("No bits were collected; nothing to save");
+        return Ok(());
+    }
+    
+    if let Some(path) = &args.bias_csv {
+        let block_size = args.bias_block.unwrap_or((bits.len() / BIAS_CSV_TARGET_BLOCKS).max(BIAS_CSV_MIN_BLOCK_BITS));
+        write_bias_csv(path, &bits, block_size)?;
+    }
+    
+    let options = ProcessOptions { header_source: args.with_header.then_some(description), ..args.process_options() };
+    let entropy_yield = process_bits(&mut bits, &options)?;
+    
+    let units = units_since(source, units_before);
+    if let Some(units) = units {
+        print_bit_yield(bits.len(), units);
+    }
+    
+    if let Some(filename) = args.output_file.as_deref().filter(|_| args.annotate) {
+        write_annotation(filename, description, args, &bits, &entropy_yield, units)?;
+    }
+    
+    if let Some(target) = &args.report_json {
+        write_report_json(&bits, target, args.generator, units)?;
+    }
+    
+    // The raw bits are used up; resuming from them again would repeat the output
+    if let Some(path) = &args.checkpoint {
+        std::fs::remove_file(path).map_err(|e| anyhow::anyhow!("Failed to remove checkpoint {path}: {e}"))?;
+        log::info!("Checkpoint {path} removed");
+    }
+    
+    Ok(())
+}
+
+/// Collect --num-bits raw bits, saving them to the checkpoint at `path` as they arrive
+///
+/// Bits saved by an earlier run are loaded first and collection continues
+/// from there. With --finalize the saved bits are returned as they are.
+///
+/// # Returns
+/// The raw bits once the target is reached (or on --finalize), `None` if the
+/// run was stopped early and the checkpoint is left to be resumed
+fn collect_checkpointed(source: &mut dyn QrngSource, path: &str, description: &str, args: &CollectArgs, cancel: &AtomicBool) -> anyhow::Result<Option<Vec<u8>>> {
+    let num_bits = args.num_bits;
+    let saved = Checkpoint::load(path).map_err(|e| anyhow::anyhow!("Failed to read checkpoint {path}: {e}"))?;
+    let mut checkpoint = match saved {
+        Some(checkpoint) if checkpoint.source != description => {
+            return Err(anyhow::anyhow!(
+                "Checkpoint {path} holds bits from {} but this run uses {description}; delete it to start over",
+                checkpoint.source
+            ));
+        },
+        Some(checkpoint) => {
+            log::info!("Resuming from checkpoint {path}: {} of {num_bits} raw bits already collected", checkpoint.bits.len());
+            checkpoint
+        },
+        None if args.finalize => return Err(anyhow::anyhow!("No checkpoint to finalize at {path}")),
+        None => Checkpoint::new(description, num_bits as u64),
+    };
+    checkpoint.target_bits = num_bits as u64;
+    
+    if args.finalize {
+        log::info!("Finalizing checkpoint {path} with {} raw bits", checkpoint.bits.len());
+        return Ok(Some(checkpoint.bits));
+    }
+    
+    let save = |checkpoint: &Checkpoint| {
+        checkpoint.save(path).map_err(|e| anyhow::anyhow!("Failed to save checkpoint {path}: {e}"))
+    };
+    // Resumed bits were health-checked by the run that collected them
+    let mut health_check = !args.no_health_check && checkpoint.bits.is_empty();
+    let mut last_save = Instant::now();
+    while checkpoint.bits.len() < num_bits && !cancel.load(Ordering::Relaxed) {
+        let chunk_bits = (num_bits - checkpoint.bits.len()).min(CHECKPOINT_CHUNK_BITS);
+        match collect(source, chunk_bits, description, health_check, cancel) {
+            Ok(chunk) => checkpoint.bits.extend(chunk),
+            Err(e) => {
+                save(&checkpoint)?;
+                return Err(e);
+            },
+        }
+        health_check = false;
+        
+        if last_save.elapsed() >= CHECKPOINT_INTERVAL {
+            save(&checkpoint)?;
+            log::info!("Checkpoint saved: {} of {num_bits} raw bits", checkpoint.bits.len());
+            last_save = Instant::now();
+        }
+    }
+    save(&checkpoint)?;
+    
+    checkpoint.bits.truncate(num_bits);
+    if checkpoint.bits.len() < num_bits {
+        log::info!(
+            "Stopped at {} of {num_bits} raw bits, saved to {path}; rerun with the same --checkpoint to continue, or add --finalize to process them now",
+            checkpoint.bits.len()
+        );
+        return Ok(None);
+    }
+    Ok(Some(checkpoint.bits))
+}
+
+/// Which side of a comparison scored better on one metric
+fn better(a: f64, b: f64, higher_is_better: bool) -> &'static str {
+    if a == b {
+        "="
+    } else if (a > b) == higher_is_better {
+        "A"
+    } else {
+        "B"
+    }
+}
+
+/// Print a side-by-side table of the randomness reports of two files
+fn compare(a: &str, b: &str, bit_order: BitOrder, allow_length_mismatch: bool) -> anyhow::Result<()> {
+    let bits_a = utils::bytes_to_bits(&std::fs::read(a)?, bit_order);
+    let bits_b = utils::bytes_to_bits(&std::fs::read(b)?, bit_order);
+    for (path, bits) in [(a, &bits_a), (b, &bits_b)] {
+        if bits.len() < 100 {
+            return Err(anyhow::anyhow!("{path} has only {} bits; at least 100 are needed to compare", bits.len()));
+        }
+    }
+    if bits_a.len() != bits_b.len() && !allow_length_mismatch {
+        return Err(anyhow::anyhow!(
+            "{a} has {} bits but {b} has {}; p-values of different lengths are not comparable (pass --allow-length-mismatch to compare anyway)",
+            bits_a.len(), bits_b.len()
+        ));
+    }
+    
+    let report_a = utils::randomness_report(&bits_a);
+    let report_b = utils::randomness_report(&bits_b);
+    println!("A: {a} ({} bits)", report_a.bit_count);
+    println!("B: {b} ({} bits)", report_b.bit_count);
+    if report_a.bit_count != report_b.bit_count {
+        log::warn!("The files differ in length; p-values of longer inputs are more sensitive");
+    }
+    println!();
+    
+    // (metric, A, B, whether a higher value is better)
+    let mut rows = vec![
+        ("Shannon entropy".to_string(), report_a.shannon_entropy, report_b.shannon_entropy, true),
+        ("Min-entropy".to_string(), report_a.min_entropy, report_b.min_entropy, true),
+        ("MCV min-entropy".to_string(), report_a.mcv_min_entropy, report_b.mcv_min_entropy, true),
+        ("N-gram entropy".to_string(), report_a.ngram_entropy, report_b.ngram_entropy, true),
+        ("Bias".to_string(), report_a.bias, report_b.bias, false),
+        ("Transition rate error".to_string(), (report_a.transition_rate - 0.5).abs(), (report_b.transition_rate - 0.5).abs(), false),
+        ("|Serial correlation|".to_string(), report_a.serial_correlation.abs(), report_b.serial_correlation.abs(), false),
+        ("|Worst autocorrelation|".to_string(), report_a.worst_autocorrelation.abs(), report_b.worst_autocorrelation.abs(), false),
+        ("Byte chi-square p".to_string(), report_a.byte_chi_square_p_value, report_b.byte_chi_square_p_value, true),
+        ("Compression ratio".to_string(), report_a.compression_ratio, report_b.compression_ratio, true),
+    ];
+    for test_a in &report_a.tests {
+        if let Some(test_b) = report_b.tests.iter().find(|test| test.name == test_a.name) {
+            rows.push((format!("NIST {} p", test_a.name), test_a.p_value, test_b.p_value, true));
+        }
+    }
+    
+    println!("{:<28} {:>10} {:>10}  Better", "Metric", "A", "B");
+    for (metric, value_a, value_b, higher_is_better) in rows {
+        println!("{metric:<28} {value_a:>10.4} {value_b:>10.4}  {}", better(value_a, value_b, higher_is_better));
+    }
+    println!("{:<28} {:>10} {:>10}", "Quality", format!("{:?}", report_a.quality), format!("{:?}", report_b.quality));
+    
+    Ok(())
+}
+
+/// Test the bits stored in a file, returning whether every test passed
+///
+/// A file written with --with-header is unpacked as its header describes,
+/// ignoring `bit_order`.
+fn verify(input: &str, bit_order: BitOrder) -> anyhow::Result<bool> {
+    let bytes = std::fs::read(input)?;
+    let bits = match OutputHeader::parse(&bytes)? {
+        Some((header, body)) => {
+            print_header(&header);
+            let mut bits = utils::bytes_to_bits(body, header.bit_order);
+            bits.truncate(header.bit_count as usize);
+            bits
+        },
+        None => utils::bytes_to_bits(&bytes, bit_order),
+    };
+    log::info!("Verifying {} bits from {input}...", bits.len());
+    
+    println!("{}", utils::quick_randomness_test(&bits));
+    if bits.len() < 100 {
+        return Ok(false);
+    }
+    
+    let passed = utils::randomness_report(&bits).all_passed();
+    println!("Verdict: {}", if passed { "PASS" } else { "FAIL" });
+    Ok(passed)
+}
+
+/// Print what the header of a verified file says about its contents
+fn print_header(header: &OutputHeader) {
+    let mut processing: Vec<&str> = Vec::new();
+    if header.debiased {
+        processing.push("debiased");
+    }
+    if header.whitened {
+        processing.push(if header.keyed { "whitened (keyed)" } else { "whitened" });
+    }
+    
+    println!("File header:");
+    println!("  Source:      {}", header.source);
+    println!("  Bits:        {}", header.bit_count);
+    println!("  Processing:  {}", if processing.is_empty() { "none (raw)".to_string() } else { processing.join(", ") });
+    println!("  Bit order:   {}", format!("{:?}", header.bit_order).to_lowercase());
+    if !header.params.is_empty() {
+        println!("  Command:     {}", header.params.join(" "));
+    }
+}
+
+/// Print every device that can be used as a source, with its command-line identifier
+fn list_devices() {
+    println!("Webcams (--camera-index):");
+    let cameras = quantum_rng::webcam::list_cameras(LIST_CAMERA_INDICES);
+    if cameras.is_empty() {
+        println!("  (none found)");
+    }
+    for camera in &cameras {
+        println!("  {:<20} {}x{}", camera.index, camera.width, camera.height);
+    }
+    
+    println!("Audio inputs (--audio-device; first is the default):");
+    match quantum_rng::audio::list_input_devices() {
+        Ok(devices) if devices.is_empty() => println!("  (none found)"),
+        Ok(devices) => {
+            for device in &devices {
+                println!("  {}", device);
+            }
+        },
+        Err(e) => println!("  (unavailable: {})", e),
+    }
+    
+    println!("Serial ports (--port):");
+    match quantum_rng::serial::list_ports() {
+        Ok(ports) if ports.is_empty() => println!("  (none found)"),
+        Ok(ports) => {
+            for port in &ports {
+                println!("  {:<20} {}", port.name, port.description);
+            }
+        },
+        Err(e) => println!("  (unavailable: {})", e),
+    }
+}
+
+/// Collect for a fixed window and report raw, debiased and hashed throughput
+///
+/// The hashed stage is SHAKE256 sized to the MCV min-entropy of the debiased
+/// bits, so it measures conditioned output rather than a fixed digest length.
+fn benchmark(source: &mut dyn QrngSource, kind: SourceKind, duration: Duration, json: bool, cancel: &AtomicBool) -> anyhow::Result<()> {
+    if !json {
+        log::info!("Benchmarking {kind:?} source for {duration:?}...");
+    }
+    
+    let start = Instant::now();
+    let raw = source.collect_for(duration, cancel)?;
+    let elapsed = start.elapsed().as_secs_f64().max(f64::EPSILON);
+    
+    let debiased = utils::von_neumann_debias(&raw);
+    let min_entropy = utils::most_common_value_estimate(&debiased, utils::MCV_REPORT_BLOCK_SIZE);
+    let hashed = utils::hash_randomness_xof(&debiased, (debiased.len() as f64 * min_entropy).floor() as usize);
+    
+    let stage = |stage, bits: usize| StageRate { stage, bits, bits_per_sec: bits as f64 / elapsed };
+    let result = BenchmarkResult {
+        source: format!("{kind:?}").to_lowercase(),
+        duration_secs: elapsed,
+        stages: vec![
+            stage("raw", raw.len()),
+            stage("debiased", debiased.len()),
+            stage("hashed", hashed.len()),
+        ],
+    };
+    
+    if json {
+        println!("{}", serde_json::to_string_pretty(&result)?);
+    } else {
+        println!("{:<10} {:>12} {:>14}", "Stage", "Bits", "Bits/sec");
+        for stage in &result.stages {
+            println!("{:<10} {:>12} {:>14.1}", stage.stage, stage.bits, stage.bits_per_sec);
+        }
+    }
+    
+    Ok(())
+}
+
+/// Collect `num_bits` bits, testing the first bits before committing to the rest of a long collection
+fn collect(source: &mut dyn QrngSource, num_bits: usize, description: &str, health_check: bool, cancel: &AtomicBool) -> anyhow::Result<Vec<u8>> {
+    if !health_check {
+        return source.collect_bits_cancellable(num_bits, cancel);
+    }
+    
+    let mut bits = source.collect_bits_cancellable(num_bits.min(HEALTH_CHECK_BITS), cancel)?;
+    check_health(&bits, description)?;
+    if bits.len() < num_bits && !cancel.load(Ordering::Relaxed) {
+        bits.extend(source.collect_bits_cancellable(num_bits - bits.len(), cancel)?);
+    }
+    
+    Ok(bits)
+}
+
+/// Write `block_index,ones_proportion` rows for the raw bits to `path`
+fn write_bias_csv(path: &str, bits: &[u8], block_size: usize) -> anyhow::Result<()> {
+    let proportions = utils::block_ones_proportions(bits, block_size);
+    let mut csv = String::from("block_index,ones_proportion\n");
+    for (index, proportion) in proportions.iter().enumerate() {
+        csv.push_str(&format!("{index},{proportion:.6}\n"));
+    }
+    std::fs::write(path, csv)?;
+    
+    log::info!("Bias of {} blocks of {block_size} raw bits saved to {path}", proportions.len());
+    Ok(())
+}
+
+/// Sensor units the source consumed since the `before` snapshot
+fn units_since(source: &dyn QrngSource, before: Option<UnitsRead>) -> Option<UnitsRead> {
+    source.units_read().map(|after| UnitsRead {
+        count: after.count - before.map_or(0, |before| before.count),
+        unit: after.unit,
+    })
+}
+
+/// Print how many output bits each consumed sensor unit yielded
+fn print_bit_yield(output_bits: usize, units: UnitsRead) {
+    if units.count == 0 {
+        return;
+    }
+    log::info!(
+        "Efficiency: {:.3} output bits per {} ({} {}s read)",
+        output_bits as f64 / units.count as f64, units.unit, units.count, units.unit
+    );
+}
+
+/// Collect raw bits behind the --tui dashboard, health-checking them afterwards
+#[cfg(feature = "tui")]
+fn collect_tui(source: &mut dyn QrngSource, num_bits: usize, description: &str, health_check: bool, cancel: &AtomicBool) -> anyhow::Result<Vec<u8>> {
+    let bits = tui::collect(source, num_bits, description, cancel)?;
+    if health_check {
+        check_health(&bits, description)?;
+    }
+    Ok(bits)
+}
+
+#[cfg(not(feature = "tui"))]
+fn collect_tui(_source: &mut dyn QrngSource, _num_bits: usize, _description: &str, _health_check: bool, _cancel: &AtomicBool) -> anyhow::Result<Vec<u8>> {
+    Err(anyhow::anyhow!("--tui needs a build with the tui feature (cargo build --features tui)"))
+}
+
+/// Write the provenance sidecar `<filename>.meta.json` for an output file
+///
+/// Records what produced the file (source, command line, post-processing,
+/// crate version and git commit), when, and the statistics of its bits.
+fn write_annotation(
+    filename: &str,
+    description: &str,
+    args: &CollectArgs,
+    bits: &[u8],
+    entropy_yield: &utils::EntropyYield,
+    units: Option<UnitsRead>,
+) -> anyhow::Result<()> {
+    let options = args.process_options();
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|since| since.as_secs())
+        .unwrap_or(0);
+    
+    let meta = serde_json::json!({
+        "output_file": filename,
+        "source": description,
+        "generator": args.generator,
+        "command_line": std::env::args().collect::<Vec<_>>(),
+        "processing": {
+            "profile": options.profile.map(|profile| format!("{profile:?}").to_lowercase()),
+            "extractor": options.extractor.map(|extractor| format!("{extractor:?}").to_lowercase()),
+            "whitening": format!("{:?}", options.whitening).to_lowercase(),
+            "hash": options.hash.name(),
+            "keyed": options.key.is_some(),
+            "mix_file": options.mix_file,
+            "mix_os_rng": options.mix_os_rng,
+            "bit_order": format!("{:?}", options.bit_order).to_lowercase(),
+        },
+        "entropy_yield": entropy_yield,
+        "units_read": units.map(|units| units.count),
+        "unit": units.map(|units| units.unit),
+        "timestamp_unix": timestamp,
+        "version": env!("CARGO_PKG_VERSION"),
+        "git_commit": option_env!("QRNG_GIT_COMMIT"),
+        "report": utils::randomness_report(bits),
+    });
+    
+    let path = format!("{filename}.meta.json");
+    std::fs::write(&path, serde_json::to_string_pretty(&meta)?)?;
+    log::info!("Metadata saved to {path}");
+    Ok(())
+}
+
+/// Serialize the randomness report for `bits` to `target` ("-" for stdout)
+///
+/// `generator` labels output that did not come straight from the hardware;
+/// `units` adds the sensor units consumed and the output bits per unit.
+fn write_report_json(bits: &[u8], target: &str, generator: Option<&str>, units: Option<UnitsRead>) -> anyhow::Result<()> {
+    let mut report = serde_json::to_value(utils::randomness_report(bits))?;
+    if let (Some(generator), Some(fields)) = (generator, report.as_object_mut()) {
+        fields.insert("generator".to_string(), generator.into());
+    }
+    if let (Some(units), Some(fields)) = (units, report.as_object_mut()) {
+        fields.insert("units_read".to_string(), units.count.into());
+        fields.insert("unit".to_string(), units.unit.into());
+        if units.count > 0 {
+            fields.insert("bits_per_unit".to_string(), (bits.len() as f64 / units.count as f64).into());
+        }
+    }
+    let json = serde_json::to_string_pretty(&report)?;
+    
+    if target == "-" {
+        println!("{json}");
+    } else {
+        std::fs::write(target, json)?;
+        log::info!("Randomness report saved to {target}");
+    }
+    
+    Ok(())
+}
+
+/// Run the SP 800-90B startup health tests, naming the source on failure
+fn check_health(bits: &[u8], description: &str) -> Result<(), QrngError> {
+    let sample = &bits[..bits.len().min(HEALTH_CHECK_BITS)];
+    let failure = |reason: &str| QrngError::HealthCheckFailed {
+        source_name: description.to_string(),
+        reason: reason.to_string(),
+    };
+    
+    if !utils::repetition_count_test(sample) {
+        return Err(failure("repetition count test found a long run of identical bits; \
+            the source appears stuck on a constant value (disconnected device or sensor fault?)"));
+    }
+    if !utils::adaptive_proportion_test(sample) {
+        return Err(failure("adaptive proportion test found one bit value dominating; \
+            the source appears stuck or severely biased"));
+    }
+    
+    Ok(())
+}
+
+/// Collect enough raw entropy for a full-entropy seed
+///
+/// A first batch sizes the collection: the raw input must carry at least
+/// `seed_bits + SEED_ENTROPY_MARGIN_BITS` bits of min-entropy (by the
+/// conservative MCV estimate) before it is squeezed through SHAKE256.
+/// Progress goes to stderr, so a printed seed can be captured by scripts.
+fn collect_seed(source: &mut dyn QrngSource, seed_bits: usize, description: &str, health_check: bool, cancel: &AtomicBool) -> anyhow::Result<Vec<u8>> {
+    log::info!("Generating a {seed_bits}-bit seed using {description}...");
+    
+    let mut bits = collect(source, HEALTH_CHECK_BITS, description, health_check, cancel)?;
+    let min_entropy = utils::most_common_value_estimate(&bits, utils::MCV_REPORT_BLOCK_SIZE);
+    if min_entropy < SEED_MIN_ENTROPY_FLOOR {
+        return Err(anyhow::anyhow!("Source min-entropy {min_entropy:.4} bits/bit is too low to generate a seed"));
+    }
+    
+    let entropy_needed = (seed_bits + SEED_ENTROPY_MARGIN_BITS) as f64;
+    let raw_needed = (entropy_needed / min_entropy).ceil() as usize;
+    log::info!("Estimated min-entropy {min_entropy:.4} bits/bit; collecting {raw_needed} raw bits");
+    
+    if bits.len() < raw_needed {
+        bits.extend(source.collect_bits_cancellable(raw_needed - bits.len(), cancel)?);
+    }
+    if bits.len() < raw_needed {
+        return Err(anyhow::anyhow!("Collection stopped after {} of {raw_needed} raw bits; no seed was generated", bits.len()));
+    }
+    
+    Ok(utils::hash_randomness_xof(&bits, seed_bits))
+}
+
+/// Hex form of a seed from `collect_seed`
+///
+/// The bits are packed back the way `hash_randomness_xof` unpacked them, so
+/// the hex is the SHAKE256 output itself, as with the default --encoding hex.
+fn seed_hex(seed: &[u8]) -> String {
+    utils::bits_to_bytes(seed, BitOrder::default()).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Whether `path` is an existing named pipe
+fn is_fifo(path: &str) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        std::fs::metadata(path).map(|metadata| metadata.file_type().is_fifo()).unwrap_or(false)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        false
+    }
+}
+
+/// Write packed bytes incrementally until `limit` bits are written or the reader closes the pipe
+///
+/// Writes block while the reader is slow, so the consumer sets the pace.
+///
+/// # Arguments
+/// * `out` - Destination (stdout or a FIFO)
+/// * `limit` - Number of bits to write, or `None` to run until cancelled
+fn stream_bits(source: &mut dyn QrngSource, out: &mut dyn Write, limit: Option<usize>, description: &str, health_check: bool, cancel: &AtomicBool) -> anyhow::Result<()> {
+    let mut pending = Vec::with_capacity(STREAM_CHUNK_BITS * 2);
+    let mut checked = !health_check;
+    let mut remaining = limit.unwrap_or(usize::MAX);
+    
+    while remaining > 0 && !cancel.load(Ordering::Relaxed) {
+        match source.read_chunk() {
+            Ok(chunk) => pending.extend(chunk),
+            Err(e) if e.is_end_of_input() => {
+                log::warn!("{e}; ending the stream");
+                break;
+            },
+            Err(e) => return Err(e.into()),
+        }
+        
+        if !checked && pending.len() >= HEALTH_CHECK_BITS {
+            check_health(&pending, description)?;
+            checked = true;
+        }
+        
+        while checked && remaining > 0 && pending.len() >= STREAM_CHUNK_BITS.min(remaining) {
+            let count = STREAM_CHUNK_BITS.min(remaining);
+            let bytes = utils::bits_to_bytes(&pending[..count], BitOrder::default());
+            pending.drain(..count);
+            remaining -= count;
+            
+            // A closed pipe (e.g. `| head -c`) is the normal way for a stream to end
+            match out.write_all(&bytes).and_then(|_| out.flush()) {
+                Ok(()) => {},
+                Err(e) if e.kind() == ErrorKind::BrokenPipe => {
+                    log::info!("Reader disconnected; stopping");
+                    return Ok(());
+                },
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+    
+    Ok(())
+}
+
+/// Read the entropy pool used by --mix-file
+fn read_mix_pool(path: &str, bit_order: BitOrder) -> anyhow::Result<Vec<u8>> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read mix file {path}: {e}"))?;
+    if bytes.is_empty() {
+        return Err(anyhow::anyhow!("Mix file {path} is empty"));
+    }
+    
+    Ok(utils::bytes_to_bits(&bytes, bit_order))
+}
+
+fn process_bits(bits: &mut Vec<u8>, options: &ProcessOptions) -> anyhow::Result<utils::EntropyYield> {
+    let original_len = bits.len();
+    let mut entropy_yield = utils::EntropyYield {
+        raw_bits: original_len,
+        debiased_bits: None,
+        whitened_bits: None,
+        raw_min_entropy: utils::estimate_min_entropy(bits),
+    };
+    
+    if let Some(profile) = options.profile {
+        log::info!("Profile {}: {}", format!("{profile:?}").to_lowercase(), profile.description());
+    }
+    
+    // Calculate entropy statistics
+    let mean = bits.iter().map(|&x| x as f64).sum::<f64>() / bits.len() as f64;
+    log::info!("Generated {original_len} bits");
+    log::info!("Raw bit mean: {mean:.4} (ideal: 0.5)");
+    log::info!("Raw min-entropy: {:.4} bits/bit (ideal: 1.0)", entropy_yield.raw_min_entropy);
+    
+    // Refuse to save output from a source that fell below the quality bar
+    if let Some(min_entropy) = options.min_entropy {
+        let entropy = utils::estimate_entropy(bits);
+        log::info!("Raw Shannon entropy: {entropy:.4} bits/bit (minimum: {min_entropy:.4})");
+        if entropy < min_entropy {
+            return Err(anyhow::anyhow!("Raw entropy {entropy:.4} is below the required minimum {min_entropy:.4}; no output was written"));
+        }
+    }
+    
+    let mut result_bits = bits.clone();
+    
+    // Combine with the saved pool before any debiasing
+    if let Some(path) = options.mix_file {
+        let pool = read_mix_pool(path, options.bit_order)?;
+        result_bits = utils::xor_mix(&result_bits, &pool);
+        log::info!("Mixed with {} bits from {path}{}", pool.len(), if pool.len() < result_bits.len() { " (cycled)" } else { "" });
+    }
+    
+    // Apply Von Neumann debiasing if requested
+    if let Some(extractor) = options.extractor {
+        result_bits = extractor.apply(&result_bits, options.symbol_bits);
+        let debiased_mean = result_bits.iter().map(|&x| x as f64).sum::<f64>() / result_bits.len().max(1) as f64;
+        log::info!("After debiasing: {} bits", result_bits.len());
+        log::info!("Debiased mean: {:.4} (ideal: 0.5)", debiased_mean);
+        entropy_yield.debiased_bits = Some(result_bits.len());
+    }
+    
+    // Apply whitening if requested
+    result_bits = options.whitening.apply(&result_bits, options.shake_output_bits(&result_bits), options.fold_passes, options.hash, options.key);
+    match options.whitening {
+        Whitening::None => {},
+        Whitening::XorFold => log::info!("After XOR folding ({} passes): {} bits", options.fold_passes, result_bits.len()),
+        Whitening::Sha3 | Whitening::Shake if options.key.is_some() => log::info!("After keyed hashing (HMAC-SHA3-256): {} bits", result_bits.len()),
+        Whitening::Sha3 => log::info!("After cryptographic hashing ({}): {} bits", options.hash.name(), result_bits.len()),
+        Whitening::Shake => log::info!("After cryptographic hashing: {} bits", result_bits.len()),
+    }
+    if options.whitening != Whitening::None {
+        entropy_yield.whitened_bits = Some(result_bits.len());
+    }
+    
+    if options.mix_os_rng {
+        result_bits = utils::mix_os_rng(&result_bits).map_err(|e| anyhow::anyhow!("The OS random number generator failed: {e}"))?;
+        log::info!("Mixed with {} bits from the OS random number generator", result_bits.len());
+    }
+    
+    print_entropy_yield(&entropy_yield);
+    
+    // Grade the final output; --strict turns a low grade into a failure
+    log::info!("{}", utils::quick_randomness_test(&result_bits));
+    if let Some(minimum) = options.strict {
+        if result_bits.len() < 100 {
+            return Err(anyhow::anyhow!("Only {} output bits, too few to grade for --strict; no output was written", result_bits.len()));
+        }
+        let quality = utils::randomness_report(&result_bits).quality;
+        if !quality.meets(minimum) {
+            return Err(anyhow::anyhow!(
+                "Output quality {quality:?} is below the required {minimum:?}; no output was written"
+            ));
+        }
+    }
+    
+    if options.print {
+        let text: String = result_bits.iter().map(|&bit| if bit == 1 { '1' } else { '0' }).collect();
+        println!("{text}");
+    }
+    
+    if let (Some(filename), Some(source)) = (options.output_file, options.header_source) {
+        let header = OutputHeader {
+            bit_count: result_bits.len() as u64,
+            debiased: options.extractor.is_some(),
+            whitened: options.whitening != Whitening::None,
+            keyed: options.key.is_some() && matches!(options.whitening, Whitening::Sha3 | Whitening::Shake),
+            bit_order: options.bit_order,
+            source: source.to_string(),
+            params: std::env::args().collect(),
+        };
+        let mut writer = OutputWriter::create(filename, Encoding::Binary, options.bit_order, false)?;
+        writer.file.write_all(&header.to_bytes())?;
+        writer.write_bits(&result_bits)?;
+        let written = writer.finish()?;
+        log::info!("Random bits saved to {filename} with a header ({written} bits)");
+    } else if let Some(filename) = options.output_file {
+        let encoding = options.encoding.unwrap_or(
+            if result_bits.len() % 8 == 0 { Encoding::Binary } else { Encoding::Text }
+        );
+        write_output(filename, &result_bits, encoding, options.bit_order, options.append)?;
+    }
+    
+    if options.stdout_binary {
+        if result_bits.len() % 8 != 0 {
+            log::warn!("{} bits is not a whole number of bytes; the last byte is zero-padded", result_bits.len());
+        }
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(&utils::bits_to_bytes(&result_bits, options.bit_order))?;
+        stdout.flush()?;
+    }
+    
+    if options.feed_kernel {
+        // Never credit more entropy than the raw input was estimated to hold
+        let entropy_bits = (entropy_yield.raw_entropy_bits() as usize).min(result_bits.len());
+        feed_kernel(&utils::bits_to_bytes(&result_bits, options.bit_order), entropy_bits)?;
+    }
+    
+    if let Some(IntegerRange { min, max, count }) = options.integers {
+        let integers = utils::bits_to_integers(&result_bits, min, max, count);
+        for value in &integers {
+            println!("{value}");
+        }
+        if integers.len() < count {
+            return Err(anyhow::anyhow!(
+                "The {} conditioned bits only yielded {} of {count} integers in [{min}, {max}]; collect more with --num-bits",
+                result_bits.len(), integers.len()
+            ));
+        }
     }
     
     *bits = result_bits;
+    Ok(entropy_yield)
+}
+
+/// Credit conditioned bytes to the kernel entropy pool
+#[cfg(target_os = "linux")]
+fn feed_kernel(bytes: &[u8], entropy_bits: usize) -> anyhow::Result<()> {
+    use quantum_rng::kernel;
+    
+    match kernel::add_entropy(bytes, entropy_bits) {
+        Ok(credited) => {
+            log::info!("Added {} bytes to the kernel entropy pool, credited with {credited} bits of entropy", bytes.len());
+            Ok(())
+        },
+        // Both EACCES (opening the device) and EPERM (the ioctl) map here
+        Err(e) if e.kind() == ErrorKind::PermissionDenied => Err(anyhow::anyhow!(
+            "Permission denied feeding {}: --feed-kernel must run as root (CAP_SYS_ADMIN)", kernel::RANDOM_DEVICE
+        )),
+        Err(e) => Err(anyhow::anyhow!("Failed to feed {}: {e}", kernel::RANDOM_DEVICE)),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn feed_kernel(_bytes: &[u8], _entropy_bits: usize) -> anyhow::Result<()> {
+    Err(anyhow::anyhow!("--feed-kernel is only supported on Linux"))
+}
+
+/// Print the raw-to-output summary of a `process_bits` run
+fn print_entropy_yield(entropy_yield: &utils::EntropyYield) {
+    log::info!("Entropy yield:");
+    log::info!("  Raw bits collected:    {}", entropy_yield.raw_bits);
+    if let Some(debiased) = entropy_yield.debiased_bits {
+        log::info!("  After debiasing:       {}", debiased);
+    }
+    if let Some(whitened) = entropy_yield.whitened_bits {
+        log::info!("  After whitening:       {}", whitened);
+    }
+    log::info!("  Raw min-entropy:       {:.4} bits/bit (~{:.0} bits total)", entropy_yield.raw_min_entropy, entropy_yield.raw_entropy_bits());
+    log::info!("  Output efficiency:     {:.2}% of raw bits", entropy_yield.efficiency() * 100.0);
+}
+
+/// Incremental encoder for --output-file
+///
+/// Bits are buffered only until they form a whole output unit (a byte, or
+/// three bytes for base64), so arbitrarily long outputs can be written in
+/// chunks with the same result as a single write.
+struct OutputWriter {
+    file: std::fs::File,
+    encoding: Encoding,
+    bit_order: BitOrder,
+    pending: Vec<u8>,
+    bits_written: usize,
+}
+
+impl OutputWriter {
+    fn create(filename: &str, encoding: Encoding, bit_order: BitOrder, append: bool) -> anyhow::Result<Self> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .append(append)
+            .truncate(!append)
+            .open(filename)?;
+        
+        Ok(OutputWriter { file, encoding, bit_order, pending: Vec::new(), bits_written: 0 })
+    }
+    
+    /// Bits per output unit that can be encoded without padding
+    fn unit_bits(&self) -> usize {
+        match self.encoding {
+            Encoding::Binary | Encoding::Hex | Encoding::BinarySts => 8,
+            Encoding::Base64 => 24,
+            Encoding::Text => 1,
+        }
+    }
+    
+    fn write_bits(&mut self, bits: &[u8]) -> anyhow::Result<()> {
+        self.pending.extend_from_slice(bits);
+        let complete = self.pending.len() / self.unit_bits() * self.unit_bits();
+        let chunk: Vec<u8> = self.pending.drain(..complete).collect();
+        self.encode(&chunk)
+    }
+    
+    /// Write any remaining (zero-padded) bits and the trailing newline of text encodings
+    ///
+    /// The NIST STS binary format drops the remaining bits instead, since
+    /// padding would feed the tests bits that were never collected.
+    fn finish(mut self) -> anyhow::Result<usize> {
+        let rest = std::mem::take(&mut self.pending);
+        if self.encoding == Encoding::BinarySts {
+            if !rest.is_empty() {
+                log::warn!("Dropped {} trailing bits that do not fill a byte of NIST STS binary output", rest.len());
+            }
+        } else {
+            self.encode(&rest)?;
+        }
+        if matches!(self.encoding, Encoding::Hex | Encoding::Base64) {
+            writeln!(self.file)?;
+        }
+        Ok(self.bits_written)
+    }
+    
+    fn encode(&mut self, bits: &[u8]) -> anyhow::Result<()> {
+        use base64::Engine;
+        
+        if bits.is_empty() {
+            return Ok(());
+        }
+        
+        match self.encoding {
+            Encoding::Binary => self.file.write_all(&utils::bits_to_bytes(bits, self.bit_order))?,
+            Encoding::Hex => {
+                let hex: String = utils::bits_to_bytes(bits, self.bit_order).iter().map(|byte| format!("{:02x}", byte)).collect();
+                self.file.write_all(hex.as_bytes())?;
+            },
+            Encoding::Base64 => {
+                let encoded = base64::engine::general_purpose::STANDARD.encode(utils::bits_to_bytes(bits, self.bit_order));
+                self.file.write_all(encoded.as_bytes())?;
+            },
+            Encoding::Text => {
+                let text: String = bits.iter().map(|&bit| if bit == 1 { '1' } else { '0' }).collect();
+                self.file.write_all(text.as_bytes())?;
+            },
+            Encoding::BinarySts => self.file.write_all(&utils::bits_to_bytes(bits, BitOrder::MsbFirst))?,
+        }
+        self.bits_written += bits.len();
+        Ok(())
+    }
+}
+
+/// Print where the output went and how large it is
+fn print_saved(filename: &str, bits: usize, encoding: Encoding, append: bool) {
+    let action = if append { "appended to" } else { "saved to" };
+    match encoding {
+        Encoding::Binary => log::info!("Random bits {action} {filename} ({} bytes)", bits.div_ceil(8)),
+        Encoding::Hex => log::info!("Random bits {action} {filename} as hex ({bits} bits)"),
+        Encoding::Base64 => log::info!("Random bits {action} {filename} as base64 ({bits} bits)"),
+        Encoding::Text => log::info!("Random bits {action} {filename} as text ({bits} bits)"),
+        Encoding::BinarySts => log::info!("Random bits {action} {filename} in NIST STS binary format ({bits} bits, {} bytes)", bits / 8),
+    }
+}
+
+/// Write bits to a file in the requested encoding
+///
+/// # Arguments
+/// * `filename` - Output file path
+/// * `bits` - Bits to write
+/// * `encoding` - Serialization format
+/// * `bit_order` - Packing order of bits within each byte (ignored for text)
+/// * `append` - Append to an existing file instead of truncating it
+fn write_output(filename: &str, bits: &[u8], encoding: Encoding, bit_order: BitOrder, append: bool) -> anyhow::Result<()> {
+    let mut writer = OutputWriter::create(filename, encoding, bit_order, append)?;
+    writer.write_bits(bits)?;
+    let written = writer.finish()?;
+    print_saved(filename, written, encoding, append);
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Run the extractor on the whole blocks of the carried bits followed by `chunk`
+///
+/// # Arguments
+/// * `extractor` - Debiasing algorithm
+/// * `symbol_bits` - Bits per Elias symbol
+/// * `carry` - Raw bits left over from the previous chunk; receives this chunk's remainder
+/// * `chunk` - Next raw bits
+/// * `last` - Whether this is the final chunk; Toeplitz then extracts the remainder too
+///
+/// # Returns
+/// Extracted bits (empty until a whole block has been gathered)
+fn extract_blocks(extractor: Extractor, symbol_bits: usize, carry: &mut Vec<u8>, chunk: Vec<u8>, last: bool) -> Vec<u8> {
+    let mut block = std::mem::take(carry);
+    block.extend(chunk);
+    
+    let block_bits = if last && extractor == Extractor::Toeplitz { 1 } else { extractor.block_bits(symbol_bits) };
+    *carry = block.split_off(block.len() / block_bits * block_bits);
+    if block.is_empty() {
+        return Vec::new();
+    }
+    
+    extractor.apply(&block, symbol_bits)
+}
+
+/// Collect, condition and write `--num-bits` raw bits one buffer at a time
+///
+/// Only one raw chunk and its processed output are held in memory. Trailing
+/// raw bits that do not fill an extractor block (a pair, or an Elias symbol)
+/// are carried into the next chunk, so classic and Elias see the same blocks
+/// as a single pass would. Peres restarts its recursion on every chunk and
+/// so yields less than a single pass; Toeplitz is run on at least
+/// `TOEPLITZ_MIN_BLOCK_BITS` raw bits at a time (see `extract_blocks`); a
+/// cancelled or exhausted collection still extracts what it carried. Each
+/// chunk is written to the unbuffered file as soon as it is processed, which
+/// is what --low-latency relies on.
+fn process_chunked(source: &mut dyn QrngSource, description: &str, args: &CollectArgs, buffer_bits: usize, cancel: &AtomicBool) -> anyhow::Result<()> {
+    let options = args.process_options();
+    let filename = options.output_file.expect("--buffer-bits requires --output-file");
+    let encoding = options.encoding.unwrap_or(Encoding::Binary);
+    let mut writer = OutputWriter::create(filename, encoding, options.bit_order, options.append)?;
+    
+    let num_bits = args.num_bits;
+    log::info!("Generating {num_bits} random bits using {description} in chunks of {buffer_bits} bits...");
+    if let Some(profile) = options.profile {
+        log::info!("Profile {}: {}", format!("{profile:?}").to_lowercase(), profile.description());
+    }
+    let start = Instant::now();
+    let units_before = source.units_read();
+    
+    let mut entropy_yield = utils::EntropyYield {
+        raw_bits: 0,
+        debiased_bits: options.extractor.map(|_| 0),
+        whitened_bits: (options.whitening != Whitening::None).then_some(0),
+        raw_min_entropy: 0.0,
+    };
+    let mut min_entropy_sum = 0.0;
+    let mut carry: Vec<u8> = Vec::new();
+    let mut debiaser = utils::VonNeumannDebiaser::new();
+    let mut health_check = !args.no_health_check;
+    let mut warned_expansion = false;
+    let mut mix_pool = options.mix_file.map(|path| read_mix_pool(path, options.bit_order)).transpose()?;
+    
+    let mut last = false;
+    while !last {
+        let request = buffer_bits.min(num_bits - entropy_yield.raw_bits);
+        let raw = collect(source, request, description, health_check, cancel)?;
+        health_check = false;
+        entropy_yield.raw_bits += raw.len();
+        // A short chunk means the collection was cancelled or the source ran
+        // out; it ends the collection all the same, so the carry is flushed
+        last = entropy_yield.raw_bits >= num_bits || raw.len() < request || cancel.load(Ordering::Relaxed);
+        min_entropy_sum += utils::estimate_min_entropy(&raw) * raw.len() as f64;
+        
+        let mut chunk = raw;
+        if let Some(pool) = mix_pool.as_mut() {
+            // Continue through the pool where the previous chunk stopped
+            chunk = utils::xor_mix(&chunk, pool);
+            let pool_len = pool.len();
+            pool.rotate_left(chunk.len() % pool_len);
+        }
+        
+        if options.extractor == Some(Extractor::Classic) {
+            chunk = debiaser.push_chunk(&chunk);
+            entropy_yield.debiased_bits = entropy_yield.debiased_bits.map(|n| n + chunk.len());
+        } else if let Some(extractor) = options.extractor {
+            chunk = extract_blocks(extractor, options.symbol_bits, &mut carry, chunk, last);
+            entropy_yield.debiased_bits = entropy_yield.debiased_bits.map(|n| n + chunk.len());
+        }
+        if chunk.is_empty() {
+            continue;
+        }
+        
+        // Chunks are conditioned independently, so only an entropy-sized length applies per chunk
+        let output_bits = if options.size_to_min_entropy { options.shake_output_bits(&chunk) } else { None };
+        let conditioned = options.whitening.apply(&chunk, output_bits, options.fold_passes, options.hash, options.key);
+        if conditioned.len() > chunk.len() && !warned_expansion {
+            warned_expansion = true;
+            log::warn!(
+                "Hashing expanded a {}-bit block to {} bits; the extra bits add no entropy. Use a larger --buffer-bits",
+                chunk.len(), conditioned.len()
+            );
+        }
+        chunk = conditioned;
+        entropy_yield.whitened_bits = entropy_yield.whitened_bits.map(|n| n + chunk.len());
+        
+        if options.mix_os_rng {
+            chunk = utils::mix_os_rng(&chunk).map_err(|e| anyhow::anyhow!("The OS random number generator failed: {e}"))?;
+        }
+        
+        writer.write_bits(&chunk)?;
+    }
+    
+    if debiaser.finish().is_some() {
+        log::debug!("Dropped the unpaired last raw bit before debiasing");
+    }
+    if !carry.is_empty() {
+        log::debug!("Dropped {} trailing raw bits that did not fill an extractor block", carry.len());
+    }
+    let written = writer.finish()?;
+    log::info!("Collection completed in {:.2?}", start.elapsed());
+    entropy_yield.raw_min_entropy = min_entropy_sum / entropy_yield.raw_bits.max(1) as f64;
+    
+    print_entropy_yield(&entropy_yield);
+    if let Some(units) = units_since(source, units_before) {
+        print_bit_yield(written, units);
+    }
+    print_saved(filename, written, encoding, options.append);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Deterministic raw bits, roughly 60% ones
+    fn raw_bits(n: usize) -> Vec<u8> {
+        (0..n as u64).map(|i| ((i.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 32) % 10 < 6) as u8).collect()
+    }
+    
+    /// Feed `input` to `extract_blocks` in chunks of `chunk_bits`
+    fn extract_chunked(extractor: Extractor, symbol_bits: usize, input: &[u8], chunk_bits: usize) -> Vec<u8> {
+        let mut carry = Vec::new();
+        let chunks = input.chunks(chunk_bits).count();
+        input.chunks(chunk_bits)
+            .enumerate()
+            .flat_map(|(i, chunk)| extract_blocks(extractor, symbol_bits, &mut carry, chunk.to_vec(), i + 1 == chunks))
+            .collect()
+    }
+    
+    #[test]
+    fn chunked_elias_matches_single_pass() {
+        let input = raw_bits(10_000);
+        for chunk_bits in [1, 37, 256, 1000] {
+            assert_eq!(extract_chunked(Extractor::Elias, 16, &input, chunk_bits), Extractor::Elias.apply(&input, 16), "chunks of {chunk_bits}");
+        }
+    }
+    
+    #[test]
+    fn chunked_toeplitz_waits_for_a_full_block() {
+        let input = raw_bits(5000);
+        let mut carry = Vec::new();
+        for chunk in input[..4000].chunks(1000) {
+            assert!(extract_blocks(Extractor::Toeplitz, 0, &mut carry, chunk.to_vec(), false).is_empty());
+        }
+        assert_eq!(carry.len(), 4000);
+        
+        let output = extract_blocks(Extractor::Toeplitz, 0, &mut carry, input[4000..].to_vec(), false);
+        assert_eq!(output, Extractor::Toeplitz.apply(&input[..TOEPLITZ_MIN_BLOCK_BITS], 0));
+        assert_eq!(carry, &input[TOEPLITZ_MIN_BLOCK_BITS..]);
+    }
+    
+    #[test]
+    fn last_toeplitz_chunk_takes_the_remainder() {
+        let input = raw_bits(5000);
+        let mut carry = input[..TOEPLITZ_MIN_BLOCK_BITS].to_vec();
+        let output = extract_blocks(Extractor::Toeplitz, 0, &mut carry, input[TOEPLITZ_MIN_BLOCK_BITS..].to_vec(), true);
+        assert_eq!(output, Extractor::Toeplitz.apply(&input, 0));
+        assert!(carry.is_empty());
+        
+        // A cancelled collection ends with an empty last chunk, which flushes a short carry
+        let mut carry = input[..4000].to_vec();
+        let output = extract_blocks(Extractor::Toeplitz, 0, &mut carry, Vec::new(), true);
+        assert_eq!(output, Extractor::Toeplitz.apply(&input[..4000], 0));
+        assert!(carry.is_empty());
+    }
+    
+    #[test]
+    fn seed_hex_is_the_shake256_output() {
+        use sha3::digest::{ExtendableOutput, Update, XofReader};
+        
+        let input = raw_bits(4096);
+        let mut hasher = sha3::Shake256::default();
+        hasher.update(&utils::bits_to_bytes(&input, BitOrder::default()));
+        let mut expected = [0u8; 32];
+        hasher.finalize_xof().read(&mut expected);
+        
+        let hex: String = expected.iter().map(|byte| format!("{:02x}", byte)).collect();
+        assert_eq!(seed_hex(&utils::hash_randomness_xof(&input, 256)), hex);
+    }
+}
@@ -0,0 +1,37 @@
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+use crate::error::Result;
+use crate::utils;
+use crate::QrngSource;
+
+/// Number of pseudo-random bytes generated per chunk
+const MOCK_CHUNK_BYTES: usize = 512;
+
+/// Deterministic stand-in for hardware, backed by a seeded ChaCha20 PRNG
+///
+/// The same seed always yields the same bits, so the full pipeline can be
+/// exercised reproducibly where no camera, microphone or Arduino exists.
+/// Its output is pseudo-random, not quantum.
+pub struct MockSource {
+    rng: ChaCha20Rng,
+}
+
+impl MockSource {
+    /// # Arguments
+    /// * `seed` - PRNG seed; equal seeds produce equal output
+    pub fn new(seed: u64) -> Self {
+        MockSource {
+            rng: ChaCha20Rng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl QrngSource for MockSource {
+    /// Generate the next block of pseudo-random bits
+    fn read_chunk(&mut self) -> Result<Vec<u8>> {
+        let mut buffer = [0u8; MOCK_CHUNK_BYTES];
+        self.rng.fill_bytes(&mut buffer);
+        Ok(utils::bytes_to_bits(&buffer, utils::BitOrder::default()))
+    }
+}
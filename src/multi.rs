@@ -0,0 +1,257 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use crate::error::{QrngError, Result};
+use crate::{AudioSource, QrngSource, SerialSource, WebcamSource};
+
+/// Upper bound on bits buffered per source while waiting for slower sources
+const MAX_QUEUED_BITS: usize = 1 << 20;
+
+/// Hardware backend run by one pool worker thread
+#[derive(Debug, Clone)]
+enum SourceKind {
+    Webcam,
+    Audio,
+    Serial(String),
+}
+
+impl SourceKind {
+    fn name(&self) -> &'static str {
+        match self {
+            SourceKind::Webcam => "webcam",
+            SourceKind::Audio => "audio",
+            SourceKind::Serial(_) => "serial",
+        }
+    }
+
+    fn build(&self) -> Box<dyn QrngSource> {
+        match self {
+            SourceKind::Webcam => Box::new(WebcamSource::new()),
+            SourceKind::Audio => Box::new(AudioSource::new()),
+            SourceKind::Serial(port) => Box::new(SerialSource::new(port.as_str())),
+        }
+    }
+}
+
+/// Messages sent from worker threads to the pool
+enum WorkerEvent {
+    /// The worker's device initialized and produced its first read
+    Ready(usize),
+    Bits(usize, Vec<u8>),
+    Failed(usize, QrngError),
+}
+
+/// Entropy pool combining webcam, audio and serial sources
+///
+/// Each available source runs in its own thread. Output bits are the XOR of
+/// one bit from every active source, so a bit is only emitted once all of
+/// them have contributed, and the result is at least as unpredictable as the
+/// best source. Sources that fail to initialize are skipped; sources that
+/// fail mid-collection are dropped from the pool with a warning.
+pub struct MultiSource {
+    kinds: Vec<SourceKind>,
+    verbose: bool,
+    started: bool,
+    receiver: Option<Receiver<WorkerEvent>>,
+    workers: Vec<JoinHandle<()>>,
+    stop: Arc<AtomicBool>,
+    queues: Vec<VecDeque<u8>>,
+    active: Vec<bool>,
+    emitted: usize,
+}
+
+impl MultiSource {
+    /// # Arguments
+    /// * `serial_port` - Serial port of an Arduino generator to include, if any
+    /// * `verbose` - Log (at trace level) which sources contributed to every output bit
+    pub fn new(serial_port: Option<&str>, verbose: bool) -> Self {
+        let mut kinds = vec![SourceKind::Webcam, SourceKind::Audio];
+        if let Some(port) = serial_port {
+            kinds.push(SourceKind::Serial(port.to_string()));
+        }
+
+        MultiSource {
+            queues: vec![VecDeque::new(); kinds.len()],
+            active: vec![false; kinds.len()],
+            kinds,
+            verbose,
+            started: false,
+            receiver: None,
+            workers: Vec::new(),
+            stop: Arc::new(AtomicBool::new(false)),
+            emitted: 0,
+        }
+    }
+
+    /// Names of the sources currently contributing to the pool
+    pub fn active_sources(&self) -> Vec<&'static str> {
+        self.kinds.iter()
+            .zip(&self.active)
+            .filter(|(_, &active)| active)
+            .map(|(kind, _)| kind.name())
+            .collect()
+    }
+
+    /// Spawn one worker per source and wait until each has initialized or failed
+    fn ensure_started(&mut self) -> Result<()> {
+        if self.started {
+            return Ok(());
+        }
+        self.started = true;
+
+        let (tx, rx) = mpsc::channel();
+        for (index, kind) in self.kinds.iter().enumerate() {
+            self.workers.push(spawn_worker(index, kind.clone(), tx.clone(), self.stop.clone()));
+        }
+        // Only workers hold senders, so recv fails once they have all exited
+        drop(tx);
+
+        let mut pending = self.kinds.len();
+        while pending > 0 {
+            match rx.recv() {
+                Ok(WorkerEvent::Ready(index)) => {
+                    log::info!("Entropy pool: {} source initialized", self.kinds[index].name());
+                    self.active[index] = true;
+                    pending -= 1;
+                },
+                Ok(WorkerEvent::Failed(index, e)) => {
+                    log::warn!("Entropy pool: {} source unavailable: {}", self.kinds[index].name(), e);
+                    pending -= 1;
+                },
+                Ok(WorkerEvent::Bits(index, bits)) => self.enqueue(index, bits),
+                Err(_) => break,
+            }
+        }
+        self.receiver = Some(rx);
+
+        match self.active_sources().as_slice() {
+            [] => Err(QrngError::PoolExhausted("No entropy sources could be initialized".to_string())),
+            [only] => {
+                log::warn!("only the {} source is available; output is not pooled", only);
+                Ok(())
+            },
+            sources => {
+                log::info!("Pooling entropy from: {}", sources.join(", "));
+                Ok(())
+            }
+        }
+    }
+
+    fn enqueue(&mut self, index: usize, bits: Vec<u8>) {
+        let queue = &mut self.queues[index];
+        let room = MAX_QUEUED_BITS.saturating_sub(queue.len());
+        queue.extend(bits.into_iter().take(room));
+    }
+
+    fn handle_event(&mut self, event: WorkerEvent) -> Result<()> {
+        match event {
+            WorkerEvent::Ready(_) => {},
+            WorkerEvent::Bits(index, bits) => self.enqueue(index, bits),
+            WorkerEvent::Failed(index, e) => {
+                log::warn!("{} source failed and was removed from the pool: {}", self.kinds[index].name(), e);
+                self.active[index] = false;
+                self.queues[index].clear();
+
+                if !self.active.contains(&true) {
+                    return Err(QrngError::PoolExhausted("All entropy sources in the pool have failed".to_string()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// XOR together one bit from each active source for as long as every active queue has data
+    fn combine(&mut self) -> Vec<u8> {
+        let mut bits = Vec::new();
+
+        while self.active.contains(&true)
+            && self.queues.iter().zip(&self.active).all(|(queue, &active)| !active || !queue.is_empty())
+        {
+            let mut bit = 0;
+            for (queue, &active) in self.queues.iter_mut().zip(&self.active) {
+                if active {
+                    bit ^= queue.pop_front().expect("queue checked non-empty");
+                }
+            }
+
+            if self.verbose {
+                log::trace!("Bit {}: {} from {}", self.emitted, bit, self.active_sources().join("+"));
+            }
+
+            self.emitted += 1;
+            bits.push(bit);
+        }
+
+        bits
+    }
+}
+
+/// Run one source in a background thread, forwarding its bits to the pool
+fn spawn_worker(index: usize, kind: SourceKind, tx: Sender<WorkerEvent>, stop: Arc<AtomicBool>) -> JoinHandle<()> {
+    thread::spawn(move || {
+        // Sources are built inside the thread because device handles are not Send
+        let mut source = kind.build();
+        let mut ready = false;
+
+        while !stop.load(Ordering::Relaxed) {
+            match source.read_chunk() {
+                Ok(bits) => {
+                    if !ready {
+                        ready = true;
+                        if tx.send(WorkerEvent::Ready(index)).is_err() {
+                            return;
+                        }
+                    }
+                    if !bits.is_empty() && tx.send(WorkerEvent::Bits(index, bits)).is_err() {
+                        return;
+                    }
+                },
+                Err(e) => {
+                    let _ = tx.send(WorkerEvent::Failed(index, e));
+                    return;
+                }
+            }
+        }
+    })
+}
+
+impl QrngSource for MultiSource {
+    /// Wait for at least one batch from the workers and return the newly pooled bits
+    fn read_chunk(&mut self) -> Result<Vec<u8>> {
+        self.ensure_started()?;
+
+        let event = self.receiver.as_ref()
+            .expect("pool started above")
+            .recv()
+            .map_err(|_| QrngError::PoolExhausted("All entropy sources in the pool have stopped".to_string()))?;
+        self.handle_event(event)?;
+
+        // Drain anything else that arrived in the meantime
+        while let Some(event) = self.receiver.as_ref().and_then(|rx| rx.try_recv().ok()) {
+            self.handle_event(event)?;
+        }
+
+        Ok(self.combine())
+    }
+
+    /// Every output bit waits for the slowest source
+    fn estimated_rate_bits_per_sec(&self) -> Option<f64> {
+        self.kinds.iter()
+            .filter_map(|kind| kind.build().estimated_rate_bits_per_sec())
+            .reduce(f64::min)
+    }
+}
+
+impl Drop for MultiSource {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        // Dropping the receiver makes workers blocked on send exit immediately
+        self.receiver = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
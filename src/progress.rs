@@ -0,0 +1,25 @@
+use indicatif::{ProgressBar, ProgressStyle};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Hide collection progress bars for the rest of the process
+///
+/// Bars are drawn on stderr and are already hidden when it is not a
+/// terminal; this additionally silences them on an interactive terminal.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// Progress bar over `total` items of `unit`, with rate and ETA
+pub(crate) fn progress_bar(total: usize, unit: &str) -> ProgressBar {
+    if QUIET.load(Ordering::Relaxed) {
+        return ProgressBar::hidden();
+    }
+
+    let template = format!("[{{elapsed_precise}}] {{bar:40}} {{pos}}/{{len}} {} ({{per_sec}}, ETA {{eta}})", unit);
+    let style = ProgressStyle::with_template(&template)
+        .unwrap_or_else(|_| ProgressStyle::default_bar())
+        .progress_chars("=> ");
+    ProgressBar::new(total as u64).with_style(style)
+}
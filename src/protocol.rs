@@ -0,0 +1,230 @@
+//! Wire protocols spoken by streaming noise hardware
+//!
+//! The Arduino over serial and an ESP32 over TCP send the same readings: one
+//! ASCII bit or byte per line (optionally several per line, optionally tagged
+//! by channel), or raw binary samples. [`StreamParser`] turns either into
+//! bits, and the retry helpers give every streaming source the same backoff.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::utils;
+
+/// Delay before the first retry; doubled after every further failure
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(100);
+
+/// Upper bound on the delay between retries
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(5);
+
+/// Consecutive failures after which the connection is closed and reopened
+pub(crate) const REOPEN_AFTER_FAILURES: u32 = 2;
+
+/// Wire format of the data sent by the firmware
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum SerialFormat {
+    /// One "0" or "1" per line (the reference sketch)
+    #[default]
+    AsciiBit,
+    /// One decimal byte value (0-255) per line, expanded to 8 bits
+    AsciiByte,
+    /// Raw binary samples of `sample_bytes` bytes; the least significant bit of each sample is extracted
+    RawByte,
+}
+
+/// Byte order of multi-byte raw samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Endian {
+    /// Least significant byte first (AVR and ARM `Serial.write` of a `uint16_t`)
+    #[default]
+    Le,
+    /// Most significant byte first
+    Be,
+}
+
+/// How readings from several tagged channels are merged into one bit stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ChannelCombine {
+    /// Take one bit from each channel in turn (throughput adds up)
+    #[default]
+    Interleave,
+    /// XOR one bit from every channel into one output bit (at least as good as the best channel)
+    Xor,
+}
+
+/// How readings are encoded on the wire
+#[derive(Debug, Clone)]
+pub struct StreamProtocol {
+    pub format: SerialFormat,
+    /// Separator between several readings on one line; `None` means one reading per line
+    pub delimiter: Option<char>,
+    /// Channel tags of firmware sending `CHANNEL:VALUE` readings, e.g. `["A", "B"]`;
+    /// empty for untagged readings. Ignored in raw-byte format
+    pub channels: Vec<String>,
+    /// How the tagged channels are merged
+    pub combine: ChannelCombine,
+    /// Bytes per unsigned sample in raw-byte format (1 or 2)
+    pub sample_bytes: u8,
+    /// Byte order of raw samples wider than one byte
+    pub endian: Endian,
+}
+
+impl Default for StreamProtocol {
+    /// One undelimited, untagged ASCII bit per line, like the reference sketch
+    fn default() -> Self {
+        StreamProtocol {
+            format: SerialFormat::default(),
+            delimiter: None,
+            channels: Vec::new(),
+            combine: ChannelCombine::default(),
+            sample_bytes: 1,
+            endian: Endian::default(),
+        }
+    }
+}
+
+/// Decodes lines or raw bytes into bits, keeping state that spans reads
+pub(crate) struct StreamParser {
+    protocol: StreamProtocol,
+    errors: usize,
+    channel_queues: Vec<VecDeque<u8>>,
+    /// Leading bytes of a raw sample split across reads
+    partial_sample: Vec<u8>,
+    /// Samples in raw-byte format, lines otherwise
+    units: usize,
+}
+
+impl StreamParser {
+    pub(crate) fn new(protocol: StreamProtocol) -> Self {
+        StreamParser {
+            channel_queues: vec![VecDeque::new(); protocol.channels.len()],
+            protocol,
+            errors: 0,
+            partial_sample: Vec::new(),
+            units: 0,
+        }
+    }
+
+    pub(crate) fn format(&self) -> SerialFormat {
+        self.protocol.format
+    }
+
+    /// Number of invalid readings received so far
+    pub(crate) fn errors(&self) -> usize {
+        self.errors
+    }
+
+    /// Samples parsed in raw-byte format, lines otherwise
+    pub(crate) fn units(&self) -> usize {
+        self.units
+    }
+
+    /// Singular name of the units counted by `units`
+    pub(crate) fn unit_name(&self) -> &'static str {
+        match (self.protocol.format, self.protocol.sample_bytes) {
+            (SerialFormat::RawByte, 1) => "byte",
+            (SerialFormat::RawByte, _) => "sample",
+            _ => "line",
+        }
+    }
+
+    /// Count an invalid reading, warning every 100
+    fn record_error(&mut self) {
+        self.errors += 1;
+        if self.errors % 100 == 0 {
+            log::warn!("Received {} invalid readings", self.errors);
+        }
+    }
+
+    /// Reassemble raw bytes into samples and extract the least significant bit of each
+    ///
+    /// A read that ends inside a sample is counted as an error (firmware should
+    /// send whole samples); its bytes are kept and completed by the next read.
+    pub(crate) fn raw_bits(&mut self, bytes: &[u8]) -> Vec<u8> {
+        let sample_bytes = self.protocol.sample_bytes.clamp(1, 2) as usize;
+        self.partial_sample.extend_from_slice(bytes);
+        let whole = self.partial_sample.len() / sample_bytes * sample_bytes;
+        if whole != self.partial_sample.len() {
+            self.record_error();
+        }
+
+        let bits: Vec<u8> = self.partial_sample[..whole]
+            .chunks_exact(sample_bytes)
+            .map(|sample| {
+                let value = match (sample_bytes, self.protocol.endian) {
+                    (1, _) => sample[0] as u16,
+                    (_, Endian::Le) => u16::from_le_bytes([sample[0], sample[1]]),
+                    (_, Endian::Be) => u16::from_be_bytes([sample[0], sample[1]]),
+                };
+                (value & 1) as u8
+            })
+            .collect();
+        self.partial_sample.drain(..whole);
+        self.units += bits.len();
+        bits
+    }
+
+    /// Decode every reading on one line of text
+    pub(crate) fn line_bits(&mut self, line: &str) -> Vec<u8> {
+        self.units += 1;
+        let line = line.trim();
+        let tokens: Vec<String> = match self.protocol.delimiter {
+            Some(delimiter) => line.split(delimiter).map(str::trim).filter(|token| !token.is_empty()).map(str::to_string).collect(),
+            None => vec![line.to_string()],
+        };
+
+        let mut bits = Vec::with_capacity(tokens.len());
+        for token in &tokens {
+            self.handle_token(token, &mut bits);
+        }
+        self.combine_channels(&mut bits);
+        bits
+    }
+
+    /// Decode one token, routing tagged readings to their channel queue
+    fn handle_token(&mut self, token: &str, bits: &mut Vec<u8>) {
+        if self.protocol.channels.is_empty() {
+            match parse_reading(self.protocol.format, token) {
+                Some(reading) => bits.extend(reading),
+                None => self.record_error(),
+            }
+            return;
+        }
+
+        let reading = token.split_once(':').and_then(|(channel, value)| {
+            let index = self.protocol.channels.iter().position(|known| known == channel.trim())?;
+            Some((index, parse_reading(self.protocol.format, value.trim())?))
+        });
+        match reading {
+            Some((index, reading)) => self.channel_queues[index].extend(reading),
+            // Unknown channel or invalid value
+            None => self.record_error(),
+        }
+    }
+
+    /// Merge queued channel bits for as long as every channel has one available
+    fn combine_channels(&mut self, bits: &mut Vec<u8>) {
+        while !self.channel_queues.is_empty() && self.channel_queues.iter().all(|queue| !queue.is_empty()) {
+            let round = self.channel_queues.iter_mut().map(|queue| queue.pop_front().expect("queue checked non-empty"));
+            match self.protocol.combine {
+                ChannelCombine::Interleave => bits.extend(round),
+                ChannelCombine::Xor => bits.push(round.fold(0, |acc, bit| acc ^ bit)),
+            }
+        }
+    }
+}
+
+/// Decode one ASCII reading according to the wire format
+fn parse_reading(format: SerialFormat, token: &str) -> Option<Vec<u8>> {
+    match (format, token.parse::<u8>()) {
+        (SerialFormat::AsciiBit, Ok(b)) if b == 0 || b == 1 => Some(vec![b]),
+        (SerialFormat::AsciiByte, Ok(byte)) => Some(utils::bytes_to_bits(&[byte], utils::BitOrder::default())),
+        _ => None,
+    }
+}
+
+/// Exponential backoff before retry number `failures` (counting from 1)
+pub(crate) fn retry_delay(failures: u32) -> Duration {
+    RETRY_BASE_DELAY
+        .saturating_mul(1 << failures.saturating_sub(1).min(16))
+        .min(RETRY_MAX_DELAY)
+}
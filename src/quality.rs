@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+use crate::error::{QrngError, Result};
+use crate::utils;
+use crate::QrngSource;
+
+/// Default number of bits evaluated at a time by a [`QualityGate`]
+pub const DEFAULT_QUALITY_WINDOW_BITS: usize = 1024;
+
+/// Smallest window a [`QualityGate`] evaluates; the entropy estimate of
+/// shorter windows is too noisy to tell a disturbance from chance
+pub const MIN_QUALITY_WINDOW_BITS: usize = 64;
+
+/// Consecutive rejected windows after which a [`QualityGate`] gives up on its source
+pub const MAX_CONSECUTIVE_REJECTIONS: usize = 100;
+
+/// Source adapter that drops low-entropy stretches of another source's output
+///
+/// Bits are grouped into fixed-size windows as they arrive; a window is
+/// passed through only if its Shannon entropy reaches the threshold, so a
+/// transient disturbance (a car driving past the microphone, a light leak
+/// into the webcam enclosure) costs a few windows instead of biasing the
+/// whole collection. A source whose output stays below the threshold for
+/// `MAX_CONSECUTIVE_REJECTIONS` windows in a row fails with
+/// [`QrngError::QualityGateRejected`] instead of stalling the collection.
+pub struct QualityGate<S: QrngSource> {
+    source: S,
+    min_entropy: f64,
+    window_bits: usize,
+    pending: Vec<u8>,
+    passed: usize,
+    rejected: usize,
+    consecutive_rejected: usize,
+}
+
+impl<S: QrngSource> QualityGate<S> {
+    /// # Arguments
+    /// * `source` - Source whose output is screened
+    /// * `min_entropy` - Minimum Shannon entropy (bits/bit) a window needs to pass
+    pub fn new(source: S, min_entropy: f64) -> Self {
+        Self::with_window(source, min_entropy, DEFAULT_QUALITY_WINDOW_BITS)
+    }
+
+    /// # Arguments
+    /// * `source` - Source whose output is screened
+    /// * `min_entropy` - Minimum Shannon entropy (bits/bit) a window needs to pass
+    /// * `window_bits` - Number of bits evaluated at a time (at least `MIN_QUALITY_WINDOW_BITS`)
+    pub fn with_window(source: S, min_entropy: f64, window_bits: usize) -> Self {
+        QualityGate {
+            source,
+            min_entropy,
+            window_bits: window_bits.max(MIN_QUALITY_WINDOW_BITS),
+            pending: Vec::new(),
+            passed: 0,
+            rejected: 0,
+            consecutive_rejected: 0,
+        }
+    }
+
+    /// Number of windows that passed the gate so far
+    pub fn passed_windows(&self) -> usize {
+        self.passed
+    }
+
+    /// Number of windows discarded so far
+    pub fn rejected_windows(&self) -> usize {
+        self.rejected
+    }
+
+    /// Consume the gate and return the underlying source
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+}
+
+impl<S: QrngSource> QrngSource for QualityGate<S> {
+    /// Read from the source and return every complete window that passed
+    fn read_chunk(&mut self) -> Result<Vec<u8>> {
+        self.pending.extend(self.source.read_chunk()?);
+
+        let mut bits = Vec::new();
+        let complete = self.pending.len() / self.window_bits * self.window_bits;
+        for window in self.pending[..complete].chunks_exact(self.window_bits) {
+            let entropy = utils::estimate_entropy(window);
+            if entropy >= self.min_entropy {
+                self.passed += 1;
+                self.consecutive_rejected = 0;
+                bits.extend_from_slice(window);
+            } else {
+                self.rejected += 1;
+                self.consecutive_rejected += 1;
+                log::debug!(
+                    "Quality gate: rejected window {} (entropy {:.4} < {:.4})",
+                    self.passed + self.rejected, entropy, self.min_entropy
+                );
+                if self.consecutive_rejected >= MAX_CONSECUTIVE_REJECTIONS {
+                    return Err(QrngError::QualityGateRejected { windows: self.consecutive_rejected, min_entropy: self.min_entropy });
+                }
+            }
+        }
+        self.pending.drain(..complete);
+
+        Ok(bits)
+    }
+
+    fn collection_timeout(&self) -> Option<Duration> {
+        self.source.collection_timeout()
+    }
+
+    fn begin_collection(&mut self) {
+        self.source.begin_collection()
+    }
+
+    /// Upper bound: rejected windows lower the actual rate
+    fn estimated_rate_bits_per_sec(&self) -> Option<f64> {
+        self.source.estimated_rate_bits_per_sec()
+    }
+
+    /// Captures everything the source reads, including rejected windows
+    fn set_raw_capture(&mut self, sink: Box<dyn std::io::Write>) -> bool {
+        self.source.set_raw_capture(sink)
+    }
+
+    /// Includes the units behind rejected windows
+    fn units_read(&self) -> Option<crate::UnitsRead> {
+        self.source.units_read()
+    }
+}
@@ -0,0 +1,94 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::error::Result;
+use crate::{QrngSource, UnitsRead};
+
+/// Source adapter that caps the average bit rate of another source
+///
+/// After every chunk the adapter sleeps until the bits read so far are no
+/// longer ahead of the target rate, so a fast source (several webcam bit
+/// planes, raw serial at high baud) stops spinning a core at full speed. A
+/// source slower than the limit is never delayed.
+pub struct RateLimiter<S: QrngSource> {
+    source: S,
+    max_rate: f64,
+    start: Option<Instant>,
+    bits: usize,
+    last_read: Option<Instant>,
+    throttled: Duration,
+}
+
+impl<S: QrngSource> RateLimiter<S> {
+    /// # Arguments
+    /// * `source` - Source whose reads are paced
+    /// * `max_rate` - Highest average rate, in raw bits per second
+    pub fn new(source: S, max_rate: f64) -> Self {
+        RateLimiter {
+            source,
+            max_rate: max_rate.max(f64::MIN_POSITIVE),
+            start: None,
+            bits: 0,
+            last_read: None,
+            throttled: Duration::ZERO,
+        }
+    }
+
+    /// Average rate from the first read to the last, in bits per second
+    pub fn achieved_rate(&self) -> Option<f64> {
+        let elapsed = self.last_read?.duration_since(self.start?).as_secs_f64();
+        (elapsed > 0.0).then(|| self.bits as f64 / elapsed)
+    }
+
+    /// Total time spent sleeping to hold the rate down
+    ///
+    /// Zero means the source itself was the bottleneck.
+    pub fn throttled_time(&self) -> Duration {
+        self.throttled
+    }
+
+    /// Consume the limiter and return the underlying source
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+}
+
+impl<S: QrngSource> QrngSource for RateLimiter<S> {
+    /// Read a chunk, then sleep off any lead over the target rate
+    fn read_chunk(&mut self) -> Result<Vec<u8>> {
+        let start = *self.start.get_or_insert_with(Instant::now);
+        let bits = self.source.read_chunk()?;
+        self.bits += bits.len();
+
+        let due = start + Duration::from_secs_f64(self.bits as f64 / self.max_rate);
+        let now = Instant::now();
+        if due > now {
+            thread::sleep(due - now);
+            self.throttled += due - now;
+        }
+        self.last_read = Some(Instant::now());
+
+        Ok(bits)
+    }
+
+    fn collection_timeout(&self) -> Option<Duration> {
+        self.source.collection_timeout()
+    }
+
+    fn begin_collection(&mut self) {
+        self.source.begin_collection()
+    }
+
+    fn estimated_rate_bits_per_sec(&self) -> Option<f64> {
+        let rate = self.source.estimated_rate_bits_per_sec().map_or(self.max_rate, |rate| rate.min(self.max_rate));
+        Some(rate)
+    }
+
+    fn set_raw_capture(&mut self, sink: Box<dyn std::io::Write>) -> bool {
+        self.source.set_raw_capture(sink)
+    }
+
+    fn units_read(&self) -> Option<UnitsRead> {
+        self.source.units_read()
+    }
+}
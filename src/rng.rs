@@ -0,0 +1,100 @@
+use rand::{Error, RngCore};
+
+use crate::error::{QrngError, Result};
+use crate::utils;
+use crate::QrngSource;
+
+/// Default number of raw bits collected per buffer refill
+pub const DEFAULT_REFILL_BITS: usize = 4096;
+
+/// Adapter exposing any [`QrngSource`] as a `rand` random number generator
+///
+/// Collected bits are buffered and packed into bytes with
+/// [`utils::bits_to_bytes`], so every output byte consumes exactly 8 raw
+/// bits from the source (no debiasing or hashing is applied). When the
+/// buffer drains the source is asked for another `refill_bits` bits.
+///
+/// Hardware sources cannot implement `SeedableRng` themselves, but a
+/// `QuantumRng` can seed any PRNG that does:
+/// `StdRng::from_rng(&mut quantum_rng)`.
+pub struct QuantumRng<S: QrngSource> {
+    source: S,
+    buffer: Vec<u8>,
+    position: usize,
+    refill_bits: usize,
+}
+
+impl<S: QrngSource> QuantumRng<S> {
+    /// # Arguments
+    /// * `source` - Hardware source to draw bits from
+    pub fn new(source: S) -> Self {
+        Self::with_refill_bits(source, DEFAULT_REFILL_BITS)
+    }
+
+    /// # Arguments
+    /// * `source` - Hardware source to draw bits from
+    /// * `refill_bits` - Raw bits collected per refill (rounded up to a multiple of 8)
+    pub fn with_refill_bits(source: S, refill_bits: usize) -> Self {
+        let refill_bits = refill_bits.max(8).div_ceil(8) * 8;
+        QuantumRng {
+            source,
+            buffer: Vec::new(),
+            position: 0,
+            refill_bits,
+        }
+    }
+
+    /// Consume the adapter and return the underlying source
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+
+    fn refill(&mut self) -> Result<()> {
+        let bits = self.source.collect_bits(self.refill_bits)?;
+        if bits.is_empty() {
+            return Err(QrngError::InsufficientData { got: 0, requested: self.refill_bits });
+        }
+
+        self.buffer = utils::bits_to_bytes(&bits, utils::BitOrder::default());
+        self.position = 0;
+        Ok(())
+    }
+}
+
+impl<S: QrngSource> RngCore for QuantumRng<S> {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0u8; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0u8; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        if let Err(e) = self.try_fill_bytes(dest) {
+            panic!("Quantum entropy source failed: {}", e);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> std::result::Result<(), Error> {
+        let mut filled = 0;
+
+        while filled < dest.len() {
+            if self.position >= self.buffer.len() {
+                self.refill().map_err(Error::new)?;
+            }
+
+            let count = (dest.len() - filled).min(self.buffer.len() - self.position);
+            dest[filled..filled + count]
+                .copy_from_slice(&self.buffer[self.position..self.position + count]);
+            self.position += count;
+            filled += count;
+        }
+
+        Ok(())
+    }
+}
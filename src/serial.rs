@@ -1,72 +1,311 @@
-use anyhow::{Result, anyhow};
 use serialport::SerialPort;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::sync::atomic::AtomicBool;
 use std::time::Duration;
 
-/// Generate random bits from Arduino-based quantum noise generator
-///
-/// # Arguments
-/// * `port_name` - Serial port name (e.g., "COM3", "/dev/ttyUSB0")
-/// * `num_bits` - Number of random bits to generate
+use crate::error::{QrngError, Result};
+use crate::protocol::{self, StreamParser, StreamProtocol, REOPEN_AFTER_FAILURES};
+use crate::{collect_chunks, QrngSource, UnitsRead};
+
+pub use crate::protocol::{ChannelCombine, Endian, SerialFormat};
+
+/// Baud rate used by the reference Arduino sketch
+pub const DEFAULT_BAUD_RATE: u32 = 9600;
+
+/// Consecutive read failures tolerated before giving up
+pub const DEFAULT_MAX_RETRIES: u32 = 5;
+
+/// Time a single read waits for data from the Arduino by default
+pub const DEFAULT_READ_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// Pause between samples in the reference sketch (`SAMPLE_DELAY` in arduino/QuantumRNG.ino)
+const REFERENCE_SAMPLE_DELAY_SECS: f64 = 0.010;
+
+/// Line bits per byte with 8N1 framing (start bit, 8 data bits, stop bit)
+const LINE_BITS_PER_BYTE: f64 = 10.0;
+
+/// Average length of an ASCII byte reading ("0" to "255") plus its CR LF
+const ASCII_BYTE_LINE_LEN: f64 = 2.57 + 2.0;
+
+/// Maximum number of bytes read at once in raw-byte mode
+const RAW_READ_SIZE: usize = 256;
+
+/// Connection settings for a serial noise source
+#[derive(Debug, Clone)]
+pub struct SerialConfig {
+    /// Serial port name (e.g., "COM3", "/dev/ttyUSB0")
+    pub port_name: String,
+    pub baud_rate: u32,
+    pub format: SerialFormat,
+    /// Consecutive read or reconnect failures tolerated before returning an error
+    pub max_retries: u32,
+    /// Separator between several readings on one line; `None` means one reading per line
+    pub delimiter: Option<char>,
+    /// Time a single read waits for data before it counts as a failure
+    pub read_timeout: Duration,
+    /// Longest time a whole collection may take; `None` waits indefinitely
+    pub collection_timeout: Option<Duration>,
+    /// Channel tags of firmware sending `CHANNEL:VALUE` readings, e.g. `["A", "B"]`;
+    /// empty for untagged readings. Ignored in raw-byte format
+    pub channels: Vec<String>,
+    /// How the tagged channels are merged
+    pub combine: ChannelCombine,
+    /// Bytes per unsigned sample in raw-byte format (1 or 2)
+    pub sample_bytes: u8,
+    /// Byte order of raw samples wider than one byte
+    pub endian: Endian,
+}
+
+impl SerialConfig {
+    /// Settings matching the reference sketch: 9600 baud, one ASCII bit per line
+    pub fn new(port_name: impl Into<String>) -> Self {
+        SerialConfig {
+            port_name: port_name.into(),
+            baud_rate: DEFAULT_BAUD_RATE,
+            format: SerialFormat::default(),
+            max_retries: DEFAULT_MAX_RETRIES,
+            delimiter: None,
+            read_timeout: DEFAULT_READ_TIMEOUT,
+            collection_timeout: None,
+            channels: Vec::new(),
+            combine: ChannelCombine::default(),
+            sample_bytes: 1,
+            endian: Endian::default(),
+        }
+    }
+
+    /// How readings are encoded on the wire
+    pub fn protocol(&self) -> StreamProtocol {
+        StreamProtocol {
+            format: self.format,
+            delimiter: self.delimiter,
+            channels: self.channels.clone(),
+            combine: self.combine,
+            sample_bytes: self.sample_bytes,
+            endian: self.endian,
+        }
+    }
+}
+
+/// Arduino-based quantum noise source connected over a serial port
 ///
-/// # Returns
-/// Vector of random bits (0s and 1s as u8)
-pub fn serial_qrng(port_name: &str, num_bits: usize) -> Result<Vec<u8>> {
-    println!("Opening serial port {}...", port_name);
-    
+/// The port is opened on first use and closed when the source is dropped.
+pub struct SerialSource {
+    config: SerialConfig,
+    reader: Option<BufReader<Box<dyn SerialPort>>>,
+    line: String,
+    parser: StreamParser,
+    failures: u32,
+    raw_capture: Option<Box<dyn Write>>,
+}
+
+impl SerialSource {
+    /// # Arguments
+    /// * `port_name` - Serial port name (e.g., "COM3", "/dev/ttyUSB0")
+    pub fn new(port_name: impl Into<String>) -> Self {
+        Self::with_config(SerialConfig::new(port_name))
+    }
+
+    /// # Arguments
+    /// * `config` - Port, baud rate and wire format to use
+    pub fn with_config(config: SerialConfig) -> Self {
+        SerialSource {
+            parser: StreamParser::new(config.protocol()),
+            config,
+            reader: None,
+            line: String::new(),
+            failures: 0,
+            raw_capture: None,
+        }
+    }
+
+    /// Number of invalid readings received so far
+    pub fn errors(&self) -> usize {
+        self.parser.errors()
+    }
+
+    /// Back off after a failed read or reconnect, reopening the port if failures persist
+    ///
+    /// Returns an error once `max_retries` consecutive failures have occurred.
+    fn retry_after(&mut self, error: QrngError) -> Result<Vec<u8>> {
+        self.failures += 1;
+        if self.failures > self.config.max_retries {
+            return Err(QrngError::SerialTimeout {
+                port: self.config.port_name.clone(),
+                retries: self.config.max_retries,
+                reason: error.to_string(),
+            });
+        }
+
+        let delay = protocol::retry_delay(self.failures);
+        log::warn!(
+            "Error reading from serial port: {} (retry {}/{} in {:?})",
+            error, self.failures, self.config.max_retries, delay
+        );
+        std::thread::sleep(delay);
+
+        // The device may have re-enumerated; drop the handle so the next read reopens it
+        if self.failures >= REOPEN_AFTER_FAILURES && self.reader.is_some() {
+            log::info!("Reopening serial port {}...", self.config.port_name);
+            self.reader = None;
+        }
+
+        Ok(Vec::new())
+    }
+}
+
+/// Open and configure the serial port connected to the Arduino
+fn open_port(config: &SerialConfig) -> Result<BufReader<Box<dyn SerialPort>>> {
+    log::info!("Opening serial port {} at {} baud...", config.port_name, config.baud_rate);
+
     // Configure serial port
-    let port = serialport::new(port_name, 9600)
-        .timeout(Duration::from_millis(1000))
+    let port = serialport::new(&config.port_name, config.baud_rate)
+        .timeout(config.read_timeout)
         .open()?;
-    
-    println!("Connected to Arduino quantum noise generator.");
-    println!("Ensure proper circuit setup: Photodiode/LED connected to analog input");
-    
-    let mut reader = BufReader::new(port); // Read data from Arduino
-    let mut bits = Vec::with_capacity(num_bits);
-    let mut line = String::new();
-    let mut errors = 0;
-    let progress_interval = num_bits / 10;
-    let mut next_progress = progress_interval;
-    
-    println!("Reading quantum noise bits from Arduino...");
-    
-    while bits.len() < num_bits {
+
+    log::info!("Connected to Arduino quantum noise generator.");
+    log::info!("Ensure proper circuit setup: Photodiode/LED connected to analog input");
+
+    Ok(BufReader::new(port)) // Read data from Arduino
+}
+
+impl QrngSource for SerialSource {
+    /// Read one line (or one buffer in raw-byte mode) from the Arduino
+    fn read_chunk(&mut self) -> Result<Vec<u8>> {
+        if self.reader.is_none() {
+            match open_port(&self.config) {
+                Ok(reader) => self.reader = Some(reader),
+                // A failed reconnect is retried; failing to open the port at all is not
+                Err(e) if self.failures > 0 => return self.retry_after(e),
+                Err(e) => return Err(e),
+            }
+            log::info!("Reading quantum noise bits from Arduino...");
+        }
+        let reader = self.reader.as_mut().expect("port opened above");
+
+        if self.config.format == SerialFormat::RawByte {
+            let mut buffer = [0u8; RAW_READ_SIZE];
+            return match reader.read(&mut buffer) {
+                // Extract least significant bit of each byte
+                Ok(count) => {
+                    self.failures = 0;
+                    if let Some(capture) = self.raw_capture.as_mut() {
+                        capture.write_all(&buffer[..count])?;
+                    }
+                    Ok(self.parser.raw_bits(&buffer[..count]))
+                },
+                Err(e) if e.kind() == ErrorKind::TimedOut => Ok(Vec::new()),
+                Err(e) => self.retry_after(e.into()),
+            };
+        }
+
         // Read a line from serial
-        line.clear();
-        match reader.read_line(&mut line) {
+        self.line.clear();
+        match reader.read_line(&mut self.line) {
+            Ok(0) => {
+                self.failures = 0;
+                Ok(Vec::new())
+            },
             Ok(_) => {
-                let bit = line.trim().parse::<u8>();
-                match bit {
-                    Ok(b) if b == 0 || b == 1 => {
-                        bits.push(b);
-                        
-                        if bits.len() >= next_progress {
-                            println!("Progress: {}%", (bits.len() * 100) / num_bits);
-                            next_progress += progress_interval;
-                        }
-                    },
-                    _ => {
-                        // Invalid data
-                        errors += 1;
-                        if errors % 100 == 0 {
-                            println!("Warning: Received {} invalid readings", errors);
-                        }
-                    }
+                self.failures = 0;
+                if let Some(capture) = self.raw_capture.as_mut() {
+                    capture.write_all(self.line.as_bytes())?;
                 }
+                Ok(self.parser.line_bits(&self.line))
             },
-            Err(e) => {
-                eprintln!("Error reading from serial port: {}", e);
-                std::thread::sleep(Duration::from_millis(100));
-            }
+            Err(e) => self.retry_after(e.into()),
         }
     }
-    
-    println!("Quantum noise collection complete");
-    
-    if errors > 0 {
-        println!("Total invalid readings: {}", errors);
+
+    /// Wire-limited throughput; the reference sketch is also paced by its sample delay
+    fn estimated_rate_bits_per_sec(&self) -> Option<f64> {
+        let bytes_per_sec = self.config.baud_rate as f64 / LINE_BITS_PER_BYTE;
+        Some(match (self.config.format, self.config.delimiter) {
+            // "0\r\n" per reading, then the sketch's delay
+            (SerialFormat::AsciiBit, None) => 1.0 / (3.0 / bytes_per_sec + REFERENCE_SAMPLE_DELAY_SECS),
+            // Batching firmware: one digit and one delimiter per reading
+            (SerialFormat::AsciiBit, Some(_)) => bytes_per_sec / 2.0,
+            (SerialFormat::AsciiByte, _) => bytes_per_sec / ASCII_BYTE_LINE_LEN * 8.0,
+            (SerialFormat::RawByte, _) => bytes_per_sec / self.config.sample_bytes.max(1) as f64,
+        })
     }
-    
-    Ok(bits[..num_bits].to_vec())
-}
\ No newline at end of file
+
+    /// Captures the bytes exactly as received, including line endings in ASCII formats
+    fn set_raw_capture(&mut self, sink: Box<dyn Write>) -> bool {
+        self.raw_capture = Some(sink);
+        true
+    }
+
+    fn units_read(&self) -> Option<UnitsRead> {
+        Some(UnitsRead { count: self.parser.units(), unit: self.parser.unit_name() })
+    }
+
+    fn collection_timeout(&self) -> Option<Duration> {
+        self.config.collection_timeout
+    }
+
+    fn collect_bits_cancellable(&mut self, num_bits: usize, cancel: &AtomicBool) -> Result<Vec<u8>> {
+        let bits = match collect_chunks(self, num_bits, cancel) {
+            Err(QrngError::Timeout { got, requested, timeout }) => {
+                log::error!("Arduino stopped delivering data: gathered {} of {} bits before the {:?} timeout", got, requested, timeout);
+                return Err(QrngError::Timeout { got, requested, timeout });
+            },
+            result => result?,
+        };
+
+        log::info!("Quantum noise collection complete");
+
+        if self.errors() > 0 {
+            log::info!("Total invalid readings: {}", self.errors());
+        }
+
+        Ok(bits)
+    }
+}
+
+/// Serial port found by `list_ports`
+#[derive(Debug, Clone)]
+pub struct PortInfo {
+    /// Name to pass as `--port`
+    pub name: String,
+    /// Human-readable description (USB product and manufacturer when known)
+    pub description: String,
+}
+
+/// Enumerate the serial ports available on this system
+///
+/// # Returns
+/// Available ports with a short description of each
+pub fn list_ports() -> Result<Vec<PortInfo>> {
+    Ok(serialport::available_ports()?
+        .into_iter()
+        .map(|port| {
+            let description = match port.port_type {
+                serialport::SerialPortType::UsbPort(usb) => {
+                    let product = usb.product.unwrap_or_else(|| "USB serial".to_string());
+                    match usb.manufacturer {
+                        Some(manufacturer) => format!("{} ({})", product, manufacturer),
+                        None => product,
+                    }
+                },
+                serialport::SerialPortType::BluetoothPort => "Bluetooth".to_string(),
+                serialport::SerialPortType::PciPort => "PCI".to_string(),
+                serialport::SerialPortType::Unknown => "Unknown".to_string(),
+            };
+            PortInfo { name: port.port_name, description }
+        })
+        .collect())
+}
+
+/// Generate random bits from Arduino-based quantum noise generator
+///
+/// # Arguments
+/// * `config` - Port, baud rate and wire format to use
+/// * `num_bits` - Number of random bits to generate
+/// * `cancel` - Flag that stops collection early when set
+///
+/// # Returns
+/// Vector of random bits (0s and 1s as u8), shorter than `num_bits` if cancelled
+pub fn serial_qrng(config: &SerialConfig, num_bits: usize, cancel: &AtomicBool) -> Result<Vec<u8>> {
+    SerialSource::with_config(config.clone()).collect_bits_cancellable(num_bits, cancel)
+}
@@ -0,0 +1,96 @@
+//! Asynchronous access to the entropy sources (requires the `async` feature)
+
+use futures_core::Stream;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::sync::mpsc;
+
+use crate::error::{QrngError, Result};
+use crate::QrngSource;
+
+/// Number of chunks buffered between the device thread and the stream
+///
+/// Once the buffer is full the device thread blocks, so a slow consumer
+/// throttles collection instead of growing memory.
+const CHANNEL_CHUNKS: usize = 4;
+
+/// Stream of raw bits produced by a source on Tokio's blocking thread pool
+///
+/// Dropping the stream stops the background loop after its current read,
+/// which drops the source and releases the device.
+pub struct BitStream {
+    receiver: mpsc::Receiver<Result<Vec<u8>>>,
+    buffer: VecDeque<u8>,
+    stop: Arc<AtomicBool>,
+}
+
+/// Run a source in the background and expose its bits as a `Stream`
+///
+/// The source is built by `make_source` on the blocking thread because
+/// device handles are not `Send`. Must be called from within a Tokio runtime.
+///
+/// # Arguments
+/// * `make_source` - Constructor for the source, e.g. `WebcamSource::new`
+///
+/// # Returns
+/// Stream yielding one bit (0 or 1) per item; it ends after the first error
+pub fn bit_stream<F, S>(make_source: F) -> BitStream
+where
+    F: FnOnce() -> S + Send + 'static,
+    S: QrngSource,
+{
+    let (sender, receiver) = mpsc::channel(CHANNEL_CHUNKS);
+    let stop = Arc::new(AtomicBool::new(false));
+    let worker_stop = stop.clone();
+
+    tokio::task::spawn_blocking(move || {
+        let mut source = make_source();
+
+        while !worker_stop.load(Ordering::Relaxed) {
+            let chunk = source.read_chunk();
+            let failed = chunk.is_err();
+            if matches!(chunk, Ok(ref bits) if bits.is_empty()) {
+                continue;
+            }
+            // Fails once the stream has been dropped
+            if sender.blocking_send(chunk).is_err() || failed {
+                break;
+            }
+        }
+    });
+
+    BitStream {
+        receiver,
+        buffer: VecDeque::new(),
+        stop,
+    }
+}
+
+impl Stream for BitStream {
+    type Item = std::result::Result<u8, QrngError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(bit) = self.buffer.pop_front() {
+                return Poll::Ready(Some(Ok(bit)));
+            }
+
+            match self.receiver.poll_recv(cx) {
+                Poll::Ready(Some(Ok(bits))) => self.buffer.extend(bits),
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl Drop for BitStream {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.receiver.close();
+    }
+}
@@ -0,0 +1,245 @@
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+
+use crate::error::{QrngError, Result};
+use crate::protocol::{self, SerialFormat, StreamParser, StreamProtocol, REOPEN_AFTER_FAILURES};
+use crate::{collect_chunks, QrngSource, UnitsRead};
+
+/// Consecutive read or reconnect failures tolerated before giving up
+pub const DEFAULT_MAX_RETRIES: u32 = 5;
+
+/// Time a connection attempt or a single read waits by default
+pub const DEFAULT_READ_TIMEOUT: Duration = Duration::from_millis(2000);
+
+/// Maximum number of bytes read at once in raw-byte mode
+const RAW_READ_SIZE: usize = 1024;
+
+/// Connection settings for a noise source streaming over TCP
+#[derive(Debug, Clone)]
+pub struct TcpConfig {
+    /// Address of the device, as `host:port`
+    pub addr: String,
+    /// How readings are encoded on the wire (the same protocols as the serial source)
+    pub protocol: StreamProtocol,
+    /// Consecutive read or reconnect failures tolerated before returning an error
+    pub max_retries: u32,
+    /// Time a connection attempt or a single read waits before it counts as a failure
+    pub read_timeout: Duration,
+    /// Longest time a whole collection may take; `None` waits indefinitely
+    pub collection_timeout: Option<Duration>,
+}
+
+impl TcpConfig {
+    /// Settings for a device sending one ASCII bit per line, like the reference sketch
+    pub fn new(addr: impl Into<String>) -> Self {
+        TcpConfig {
+            addr: addr.into(),
+            protocol: StreamProtocol::default(),
+            max_retries: DEFAULT_MAX_RETRIES,
+            read_timeout: DEFAULT_READ_TIMEOUT,
+            collection_timeout: None,
+        }
+    }
+}
+
+/// Networked noise source (e.g. an ESP32 on Wi-Fi) streaming readings over TCP
+///
+/// The connection is made on first use; when it drops, the source backs off
+/// and reconnects like the serial source does.
+pub struct TcpSource {
+    config: TcpConfig,
+    reader: Option<BufReader<TcpStream>>,
+    line: String,
+    parser: StreamParser,
+    failures: u32,
+    raw_capture: Option<Box<dyn Write>>,
+}
+
+impl TcpSource {
+    /// # Arguments
+    /// * `addr` - Address of the device, as `host:port`
+    pub fn new(addr: impl Into<String>) -> Self {
+        Self::with_config(TcpConfig::new(addr))
+    }
+
+    /// # Arguments
+    /// * `config` - Address and wire format to use
+    pub fn with_config(config: TcpConfig) -> Self {
+        TcpSource {
+            parser: StreamParser::new(config.protocol.clone()),
+            config,
+            reader: None,
+            line: String::new(),
+            failures: 0,
+            raw_capture: None,
+        }
+    }
+
+    /// Number of invalid readings received so far
+    pub fn errors(&self) -> usize {
+        self.parser.errors()
+    }
+
+    /// Back off after a failed read or reconnect, reconnecting if failures persist
+    ///
+    /// Returns an error once `max_retries` consecutive failures have occurred.
+    fn retry_after(&mut self, error: QrngError) -> Result<Vec<u8>> {
+        self.failures += 1;
+        if self.failures > self.config.max_retries {
+            return Err(QrngError::ConnectionLost {
+                addr: self.config.addr.clone(),
+                retries: self.config.max_retries,
+                reason: error.to_string(),
+            });
+        }
+
+        let delay = protocol::retry_delay(self.failures);
+        log::warn!(
+            "Error reading from {}: {} (retry {}/{} in {:?})",
+            self.config.addr, error, self.failures, self.config.max_retries, delay
+        );
+        std::thread::sleep(delay);
+
+        if self.failures >= REOPEN_AFTER_FAILURES && self.reader.is_some() {
+            log::info!("Reconnecting to {}...", self.config.addr);
+            self.reader = None;
+        }
+
+        Ok(Vec::new())
+    }
+
+    /// A closed connection cannot deliver more data, so reconnect on the next read
+    fn connection_closed(&mut self) -> Result<Vec<u8>> {
+        self.reader = None;
+        self.line.clear();
+        self.retry_after(std::io::Error::new(ErrorKind::ConnectionReset, "connection closed by the device").into())
+    }
+}
+
+/// Connect to the device, trying each address the host name resolves to
+fn connect(config: &TcpConfig) -> Result<BufReader<TcpStream>> {
+    log::info!("Connecting to {}...", config.addr);
+
+    let addrs = config.addr.to_socket_addrs()
+        .map_err(|e| QrngError::NoDevice(format!("Cannot resolve {}: {}", config.addr, e)))?;
+    let mut last_error = None;
+    for addr in addrs {
+        match TcpStream::connect_timeout(&addr, config.read_timeout) {
+            Ok(stream) => {
+                stream.set_read_timeout(Some(config.read_timeout))?;
+                stream.set_nodelay(true)?;
+                log::info!("Connected to noise source at {}", addr);
+                return Ok(BufReader::new(stream));
+            },
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    Err(QrngError::NoDevice(match last_error {
+        Some(e) => format!("Cannot connect to {}: {}", config.addr, e),
+        None => format!("Cannot connect to {}: no addresses found", config.addr),
+    }))
+}
+
+/// Whether a read error only means no data arrived within the read timeout
+fn is_timeout(error: &std::io::Error) -> bool {
+    matches!(error.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock)
+}
+
+impl QrngSource for TcpSource {
+    /// Read one line (or one buffer in raw-byte mode) from the connection
+    fn read_chunk(&mut self) -> Result<Vec<u8>> {
+        if self.reader.is_none() {
+            match connect(&self.config) {
+                Ok(reader) => self.reader = Some(reader),
+                // A failed reconnect is retried; failing to connect at all is not
+                Err(e) if self.failures > 0 => return self.retry_after(e),
+                Err(e) => return Err(e),
+            }
+        }
+        let reader = self.reader.as_mut().expect("connected above");
+
+        if self.parser.format() == SerialFormat::RawByte {
+            let mut buffer = [0u8; RAW_READ_SIZE];
+            return match reader.read(&mut buffer) {
+                Ok(0) => self.connection_closed(),
+                Ok(count) => {
+                    self.failures = 0;
+                    if let Some(capture) = self.raw_capture.as_mut() {
+                        capture.write_all(&buffer[..count])?;
+                    }
+                    Ok(self.parser.raw_bits(&buffer[..count]))
+                },
+                Err(e) if is_timeout(&e) => Ok(Vec::new()),
+                Err(e) => self.retry_after(e.into()),
+            };
+        }
+
+        // A line cut short by the read timeout stays buffered and is completed by the next read
+        match reader.read_line(&mut self.line) {
+            Ok(0) => self.connection_closed(),
+            Ok(_) if !self.line.ends_with('\n') => self.connection_closed(),
+            Ok(_) => {
+                self.failures = 0;
+                if let Some(capture) = self.raw_capture.as_mut() {
+                    capture.write_all(self.line.as_bytes())?;
+                }
+                let bits = self.parser.line_bits(&self.line);
+                self.line.clear();
+                Ok(bits)
+            },
+            Err(e) if is_timeout(&e) => Ok(Vec::new()),
+            Err(e) => {
+                self.line.clear();
+                self.retry_after(e.into())
+            },
+        }
+    }
+
+    /// Captures the bytes exactly as received, including line endings in ASCII formats
+    fn set_raw_capture(&mut self, sink: Box<dyn Write>) -> bool {
+        self.raw_capture = Some(sink);
+        true
+    }
+
+    fn units_read(&self) -> Option<UnitsRead> {
+        Some(UnitsRead { count: self.parser.units(), unit: self.parser.unit_name() })
+    }
+
+    fn collection_timeout(&self) -> Option<Duration> {
+        self.config.collection_timeout
+    }
+
+    fn collect_bits_cancellable(&mut self, num_bits: usize, cancel: &AtomicBool) -> Result<Vec<u8>> {
+        let bits = match collect_chunks(self, num_bits, cancel) {
+            Err(QrngError::Timeout { got, requested, timeout }) => {
+                log::error!("{} stopped delivering data: gathered {} of {} bits before the {:?} timeout", self.config.addr, got, requested, timeout);
+                return Err(QrngError::Timeout { got, requested, timeout });
+            },
+            result => result?,
+        };
+
+        log::info!("Network noise collection complete");
+
+        if self.errors() > 0 {
+            log::info!("Total invalid readings: {}", self.errors());
+        }
+
+        Ok(bits)
+    }
+}
+
+/// Generate random bits from a noise source streaming over TCP
+///
+/// # Arguments
+/// * `config` - Address and wire format to use
+/// * `num_bits` - Number of random bits to generate
+/// * `cancel` - Flag that stops collection early when set
+///
+/// # Returns
+/// Vector of random bits (0s and 1s as u8), shorter than `num_bits` if cancelled
+pub fn tcp_qrng(config: &TcpConfig, num_bits: usize, cancel: &AtomicBool) -> Result<Vec<u8>> {
+    TcpSource::with_config(config.clone()).collect_bits_cancellable(num_bits, cancel)
+}
@@ -0,0 +1,221 @@
+//! Live terminal dashboard shown while collecting with --tui
+//!
+//! Reads the source chunk by chunk and redraws bias, entropy and throughput
+//! as the bits arrive. Built only with the `tui` feature.
+
+use std::collections::VecDeque;
+use std::io::Stdout;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use quantum_rng::{utils, QrngSource};
+use ratatui::backend::CrosstermBackend;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::crossterm::execute;
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Style};
+use ratatui::widgets::{Block, Borders, Gauge, Paragraph, Sparkline};
+use ratatui::Terminal;
+
+/// Bits per point of the bias sparkline
+const BIAS_WINDOW_BITS: usize = 256;
+
+/// Bias drawn at full sparkline height, in thousandths
+const BIAS_SCALE_MILLIS: u64 = 100;
+
+/// Sparkline points kept (wider than any terminal, the widget shows the newest)
+const SPARKLINE_POINTS: usize = 512;
+
+/// Most recent bits used for the entropy estimates
+const ENTROPY_WINDOW_BITS: usize = 4096;
+
+/// Minimum time between redraws
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Restores the terminal when collection ends, including on errors
+struct TerminalGuard {
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+    log_level: log::LevelFilter,
+}
+
+impl TerminalGuard {
+    fn new() -> anyhow::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let mut stdout = std::io::stdout();
+        execute!(stdout, EnterAlternateScreen)?;
+        let terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+        // Log lines on stderr would scribble over the dashboard
+        let log_level = log::max_level();
+        log::set_max_level(log::LevelFilter::Off);
+
+        Ok(TerminalGuard { terminal, log_level })
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+        let _ = execute!(self.terminal.backend_mut(), LeaveAlternateScreen);
+        let _ = self.terminal.show_cursor();
+        log::set_max_level(self.log_level);
+    }
+}
+
+/// Running statistics drawn on the dashboard
+struct Dashboard<'a> {
+    description: &'a str,
+    num_bits: usize,
+    collected: usize,
+    start: Instant,
+    bias: VecDeque<u64>,
+    window_ones: usize,
+    window_len: usize,
+    recent: VecDeque<u8>,
+}
+
+impl<'a> Dashboard<'a> {
+    fn new(description: &'a str, num_bits: usize) -> Self {
+        Dashboard {
+            description,
+            num_bits,
+            collected: 0,
+            start: Instant::now(),
+            bias: VecDeque::with_capacity(SPARKLINE_POINTS),
+            window_ones: 0,
+            window_len: 0,
+            recent: VecDeque::with_capacity(ENTROPY_WINDOW_BITS),
+        }
+    }
+
+    fn add(&mut self, bits: &[u8]) {
+        self.collected += bits.len();
+        for &bit in bits {
+            self.window_ones += bit as usize;
+            self.window_len += 1;
+            if self.window_len == BIAS_WINDOW_BITS {
+                let mean = self.window_ones as f64 / BIAS_WINDOW_BITS as f64;
+                if self.bias.len() == SPARKLINE_POINTS {
+                    self.bias.pop_front();
+                }
+                self.bias.push_back(((mean - 0.5).abs() * 1000.0).round() as u64);
+                self.window_ones = 0;
+                self.window_len = 0;
+            }
+
+            if self.recent.len() == ENTROPY_WINDOW_BITS {
+                self.recent.pop_front();
+            }
+            self.recent.push_back(bit);
+        }
+    }
+
+    fn draw(&self, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> anyhow::Result<()> {
+        let elapsed = self.start.elapsed().as_secs_f64().max(f64::EPSILON);
+        let recent: Vec<u8> = self.recent.iter().copied().collect();
+        let (entropy, min_entropy, mean) = if recent.is_empty() {
+            (0.0, 0.0, 0.0)
+        } else {
+            (
+                utils::estimate_entropy(&recent),
+                utils::most_common_value_estimate(&recent, utils::MCV_REPORT_BLOCK_SIZE),
+                recent.iter().map(|&bit| bit as f64).sum::<f64>() / recent.len() as f64,
+            )
+        };
+        let ratio = (self.collected as f64 / self.num_bits.max(1) as f64).min(1.0);
+        let bias: Vec<u64> = self.bias.iter().copied().collect();
+
+        terminal.draw(|frame| {
+            let areas = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(3), Constraint::Length(7), Constraint::Min(5)])
+                .split(frame.size());
+
+            let gauge = Gauge::default()
+                .block(Block::default().borders(Borders::ALL).title(format!(" {} (q to stop) ", self.description)))
+                .gauge_style(Style::default().fg(Color::Cyan))
+                .ratio(ratio)
+                .label(format!("{} / {} bits", self.collected, self.num_bits));
+            frame.render_widget(gauge, areas[0]);
+
+            let stats = Paragraph::new(format!(
+                "Throughput:      {:.1} bits/sec\n\
+                 Shannon entropy: {entropy:.4} bits/bit (last {} bits)\n\
+                 Min-entropy:     {min_entropy:.4} bits/bit (MCV)\n\
+                 Mean:            {mean:.4} (ideal: 0.5)\n\
+                 Elapsed:         {:.1}s",
+                self.collected as f64 / elapsed, recent.len(), elapsed,
+            ))
+            .block(Block::default().borders(Borders::ALL).title(" Statistics "));
+            frame.render_widget(stats, areas[1]);
+
+            // Newest windows on the right, as many as fit inside the borders
+            let width = areas[2].width.saturating_sub(2) as usize;
+            let sparkline = Sparkline::default()
+                .block(Block::default().borders(Borders::ALL).title(format!(
+                    " Bias per {BIAS_WINDOW_BITS}-bit window (full height = {:.2}) ",
+                    BIAS_SCALE_MILLIS as f64 / 1000.0
+                )))
+                .data(&bias[bias.len().saturating_sub(width)..])
+                .max(BIAS_SCALE_MILLIS)
+                .style(Style::default().fg(Color::Yellow));
+            frame.render_widget(sparkline, areas[2]);
+        })?;
+
+        Ok(())
+    }
+}
+
+/// Whether the user pressed q, Esc or Ctrl-C (raw mode swallows the signal)
+fn stop_requested() -> anyhow::Result<bool> {
+    while event::poll(Duration::ZERO)? {
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            let ctrl_c = key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+            if ctrl_c || matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+/// Collect `num_bits` raw bits while showing the live dashboard
+///
+/// # Arguments
+/// * `source` - Source to read chunks from
+/// * `num_bits` - Number of raw bits to collect
+/// * `description` - Source name shown in the title
+/// * `cancel` - Stops collection early, as does pressing q
+///
+/// # Returns
+/// The bits collected, fewer than requested if stopped early
+pub fn collect(source: &mut dyn QrngSource, num_bits: usize, description: &str, cancel: &AtomicBool) -> anyhow::Result<Vec<u8>> {
+    let mut guard = TerminalGuard::new()?;
+    let mut dashboard = Dashboard::new(description, num_bits);
+    let mut bits = Vec::with_capacity(num_bits);
+    let mut last_draw: Option<Instant> = None;
+
+    while bits.len() < num_bits && !cancel.load(Ordering::Relaxed) {
+        if stop_requested()? {
+            cancel.store(true, Ordering::Relaxed);
+            break;
+        }
+
+        let mut chunk = source.read_chunk()?;
+        chunk.truncate(num_bits - bits.len());
+        dashboard.add(&chunk);
+        bits.extend_from_slice(&chunk);
+
+        if last_draw.is_none_or(|last| last.elapsed() >= REDRAW_INTERVAL) {
+            dashboard.draw(&mut guard.terminal)?;
+            last_draw = Some(Instant::now());
+        }
+    }
+    dashboard.draw(&mut guard.terminal)?;
+
+    Ok(bits)
+}
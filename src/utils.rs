@@ -1,4 +1,74 @@
-use sha3::{Sha3_256, Digest};
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use hmac::{Hmac, Mac};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use rayon::prelude::*;
+use serde::Serialize;
+use sha3::digest::{ExtendableOutput, XofReader};
+use sha3::{Sha3_256, Sha3_512, Shake256, Digest};
+
+/// Significance level used to decide pass/fail for NIST SP 800-22 tests
+pub const NIST_SIGNIFICANCE: f64 = 0.01;
+
+/// Assumed min-entropy per raw bit used to size the SP 800-90B health test cutoffs
+pub const HEALTH_ASSUMED_MIN_ENTROPY: f64 = 0.5;
+
+/// Health test false positive probability, as a power of two (alpha = 2^-20)
+pub const HEALTH_ALPHA_EXPONENT: f64 = 20.0;
+
+/// Window size of the adaptive proportion test for binary samples
+pub const APT_WINDOW_SIZE: usize = 1024;
+
+/// Symbol size used for the MCV min-entropy estimate in randomness reports
+pub const MCV_REPORT_BLOCK_SIZE: usize = 1;
+
+/// Largest lag scanned for periodic structure by `worst_autocorrelation`
+pub const AUTOCORRELATION_MAX_LAG: usize = 64;
+
+/// Largest pattern length used for the n-gram entropy in randomness reports
+pub const NGRAM_REPORT_MAX_SIZE: usize = 8;
+
+/// Overlapping samples per possible pattern needed before a pattern length is
+/// used in reports; fewer samples bias the plug-in entropy estimate low
+const NGRAM_SAMPLES_PER_PATTERN: usize = 16;
+
+/// N-gram entropy (bits/bit) below which a report is graded poor
+pub const NGRAM_ENTROPY_FLOOR: f64 = 0.9;
+
+/// Default symbol size of the Elias extractor
+pub const ELIAS_DEFAULT_SYMBOL_BITS: usize = 32;
+
+/// Largest Elias symbol size whose rank fits comfortably in a u64
+pub const ELIAS_MAX_SYMBOL_BITS: usize = 62;
+
+/// Security parameter of the Toeplitz extractor: output is within 2^-64 of uniform
+pub const TOEPLITZ_SECURITY_BITS: usize = 64;
+
+/// Public seed from which the Toeplitz matrix diagonals are derived
+///
+/// A seeded extractor only needs its seed to be independent of the source,
+/// not secret, so a fixed published seed is sufficient.
+const TOEPLITZ_SEED: &[u8] = b"QuantumRNG Toeplitz extractor seed v1";
+
+/// Outcome of a single statistical test
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TestResult {
+    pub name: String,
+    pub p_value: f64,
+    pub passed: bool,
+}
+
+impl TestResult {
+    /// Build a result, marking it as passed when `p_value` reaches the NIST significance level
+    pub fn new(name: impl Into<String>, p_value: f64) -> Self {
+        TestResult {
+            name: name.into(),
+            p_value,
+            passed: p_value >= NIST_SIGNIFICANCE,
+        }
+    }
+}
 
 /// Implements Von Neumann debiasing algorithm
 /// Takes pairs of bits and outputs either nothing (if the bits are equal) 
@@ -29,7 +99,7 @@ pub fn von_neumann_debias(bits: &[u8]) -> Vec<u8> {
     
     // Ensure we have some output
     if result.is_empty() && !bits.is_empty() {
-        println!("Warning: Von Neumann debiasing produced zero bits. Input may have low entropy.");
+        log::warn!("Von Neumann debiasing produced zero bits. Input may have low entropy.");
         // Return at least one bit to avoid crashes
         result.push(bits[0]);
     }
@@ -37,6 +107,248 @@ pub fn von_neumann_debias(bits: &[u8]) -> Vec<u8> {
     result
 }
 
+/// Von Neumann debiasing over input that arrives in chunks
+///
+/// An odd-length chunk leaves its last bit unpaired; it is held back and
+/// paired with the first bit of the next chunk, so the output is the same
+/// however the input is split. Unlike `von_neumann_debias`, a chunk that
+/// yields no bits yields nothing rather than a fallback bit.
+#[derive(Debug, Clone, Default)]
+pub struct VonNeumannDebiaser {
+    pending: Option<u8>,
+}
+
+impl VonNeumannDebiaser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Debias the next chunk of input
+    ///
+    /// # Arguments
+    /// * `bits` - Next input bits, continuing the previous chunk
+    ///
+    /// # Returns
+    /// The first bit of every unequal pair completed by this chunk
+    pub fn push_chunk(&mut self, bits: &[u8]) -> Vec<u8> {
+        let mut result = Vec::with_capacity(bits.len() / 4);
+        
+        for &bit in bits {
+            match self.pending.take() {
+                Some(first) if first != bit => result.push(first),
+                Some(_) => {},
+                None => self.pending = Some(bit),
+            }
+        }
+        
+        result
+    }
+    
+    /// End the input
+    ///
+    /// # Returns
+    /// The trailing unpaired bit, if any; it carries no debiased output and
+    /// is dropped just as `von_neumann_debias` drops the last bit of odd input
+    pub fn finish(self) -> Option<u8> {
+        self.pending
+    }
+}
+
+/// Binomial coefficient C(n, k)
+fn binomial(n: usize, k: usize) -> u64 {
+    if k > n {
+        return 0;
+    }
+    
+    let k = k.min(n - k);
+    let mut result: u128 = 1;
+    for i in 0..k {
+        result = result * (n - i) as u128 / (i + 1) as u128;
+    }
+    result as u64
+}
+
+/// Implements Elias' extractor, the generalization of Von Neumann to symbols
+/// For i.i.d. input every `symbol_bits`-bit block with k ones is as likely
+/// as any other with k ones, so the rank of a block among those C(n, k)
+/// arrangements is uniform. The rank is mapped to output bits by splitting
+/// C(n, k) into powers of two. With 2-bit symbols this is classic Von
+/// Neumann; longer symbols approach the Shannon entropy of the input
+/// (about 90% of it at 62 bits for a 70/30 source, against 27% for pairs).
+/// 3-bit symbols are the exception: they keep only two thirds of what pairs do.
+/// Trailing bits that do not fill a symbol are dropped.
+///
+/// # Arguments
+/// * `bits` - Input bit sequence
+/// * `symbol_bits` - Bits per symbol (clamped to 2-`ELIAS_MAX_SYMBOL_BITS`)
+///
+/// # Returns
+/// Debiased bit sequence
+pub fn elias_extract(bits: &[u8], symbol_bits: usize) -> Vec<u8> {
+    let n = symbol_bits.clamp(2, ELIAS_MAX_SYMBOL_BITS);
+    let mut result = Vec::with_capacity(bits.len() / 2);
+    
+    for block in bits.chunks_exact(n) {
+        // Lexicographic rank of the block among arrangements with the same number of ones
+        let ones = block.iter().filter(|&&b| b == 1).count();
+        let mut rank = 0u64;
+        let mut remaining = ones;
+        for (i, &bit) in block.iter().enumerate() {
+            if bit == 1 {
+                rank += binomial(n - i - 1, remaining);
+                remaining -= 1;
+            }
+        }
+        
+        // Find the power-of-two interval of C(n, k) holding the rank and emit its offset
+        let mut count = binomial(n, ones);
+        while count > 0 {
+            let width = 63 - count.leading_zeros() as usize;
+            let interval = 1u64 << width;
+            if rank < interval {
+                for shift in (0..width).rev() {
+                    result.push(((rank >> shift) & 1) as u8);
+                }
+                break;
+            }
+            rank -= interval;
+            count -= interval;
+        }
+    }
+    
+    result
+}
+
+/// Implements Peres' iterated Von Neumann extractor
+/// Applies classic Von Neumann debiasing, then recursively extracts the
+/// information left in the discarded pairs: the sequence of values from
+/// equal pairs, and the sequence recording whether each pair was equal.
+/// For biased sources this recovers far more bits than a single pass
+/// (approaching the Shannon entropy of the input as the recursion deepens).
+///
+/// # Arguments
+/// * `bits` - Input bit sequence
+///
+/// # Returns
+/// Debiased bit sequence
+pub fn von_neumann_debias_peres(bits: &[u8]) -> Vec<u8> {
+    let result = peres_extract(bits);
+    
+    if result.is_empty() && !bits.is_empty() {
+        log::warn!("Peres debiasing produced zero bits. Input may have low entropy.");
+    }
+    
+    result
+}
+
+fn peres_extract(bits: &[u8]) -> Vec<u8> {
+    if bits.len() < 2 {
+        return Vec::new();
+    }
+    
+    let pairs = bits.len() / 2;
+    let mut result = Vec::with_capacity(pairs / 2);
+    let mut equal_values = Vec::with_capacity(pairs / 2);
+    let mut pair_xors = Vec::with_capacity(pairs);
+    
+    for pair in bits.chunks_exact(2) {
+        let (a, b) = (pair[0], pair[1]);
+        
+        if a != b {
+            result.push(a);
+        } else {
+            equal_values.push(a);
+        }
+        pair_xors.push(a ^ b);
+    }
+    
+    result.extend(peres_extract(&equal_values));
+    result.extend(peres_extract(&pair_xors));
+    result
+}
+
+/// Toeplitz-matrix randomness extractor (a 2-universal hash family)
+/// Multiplies the input by an m x n Toeplitz matrix over GF(2). By the
+/// leftover hash lemma, an input with k = n * H bits of min-entropy yields
+/// m = k - 2 * `TOEPLITZ_SECURITY_BITS` output bits that are 2^-64-close to
+/// uniform, an information-theoretic guarantee that SHA-3 does not give.
+/// The matrix is generated from a short fixed seed expanded with SHAKE256.
+///
+/// # Arguments
+/// * `bits` - Input bit sequence
+/// * `input_min_entropy` - Min-entropy of the input in bits per bit (use a conservative estimate)
+///
+/// # Returns
+/// Extracted bits (empty if the entropy budget does not cover the security margin)
+pub fn toeplitz_extract(bits: &[u8], input_min_entropy: f64) -> Vec<u8> {
+    let n = bits.len();
+    let entropy_budget = (n as f64 * input_min_entropy.clamp(0.0, 1.0)).floor() as usize;
+    let m = entropy_budget.saturating_sub(2 * TOEPLITZ_SECURITY_BITS);
+    if m == 0 {
+        log::warn!("Toeplitz extraction produced zero bits. Input entropy ({} bits) does not cover the security margin.", entropy_budget);
+        return Vec::new();
+    }
+    
+    // Row i of the matrix is the window diagonal[m-1-i .. m-1-i+n]
+    let diagonal = hash_randomness_xof(&bytes_to_bits(TOEPLITZ_SEED, BitOrder::default()), n + m - 1);
+    let input_words = pack_words(bits);
+    let diagonal_words = pack_words(&diagonal);
+    
+    (0..m).into_par_iter()
+        .map(|i| {
+            let offset = m - 1 - i;
+            let ones: u32 = input_words.iter()
+                .enumerate()
+                .map(|(w, &word)| (word & window_word(&diagonal_words, offset + 64 * w)).count_ones())
+                .sum();
+            (ones & 1) as u8
+        })
+        .collect()
+}
+
+/// Pack bits into 64-bit words, first bit in the least significant position
+fn pack_words(bits: &[u8]) -> Vec<u64> {
+    bits.chunks(64)
+        .map(|chunk| chunk.iter().enumerate().fold(0u64, |word, (i, &bit)| word | ((bit as u64 & 1) << i)))
+        .collect()
+}
+
+/// The 64 packed bits starting at `bit_offset`, zero past the end
+fn window_word(words: &[u64], bit_offset: usize) -> u64 {
+    let (index, shift) = (bit_offset / 64, bit_offset % 64);
+    let low = words.get(index).copied().unwrap_or(0) >> shift;
+    let high = match shift {
+        0 => 0,
+        _ => words.get(index + 1).copied().unwrap_or(0) << (64 - shift),
+    };
+    low | high
+}
+
+/// Hash function used by `hash_randomness_with`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum HashAlgo {
+    /// SHA3-256 (256 output bits, the historical default)
+    #[default]
+    #[value(name = "sha3-256")]
+    Sha3_256,
+    /// SHA3-512 (512 output bits)
+    #[value(name = "sha3-512")]
+    Sha3_512,
+    /// BLAKE3 in XOF mode (as many output bits as input bits, and fast)
+    Blake3,
+}
+
+impl HashAlgo {
+    /// Display name of the algorithm
+    pub fn name(self) -> &'static str {
+        match self {
+            HashAlgo::Sha3_256 => "SHA3-256",
+            HashAlgo::Sha3_512 => "SHA3-512",
+            HashAlgo::Blake3 => "BLAKE3",
+        }
+    }
+}
+
 /// Cryptographically hash the random bits using SHA-3
 ///
 /// # Arguments
@@ -45,37 +357,273 @@ pub fn von_neumann_debias(bits: &[u8]) -> Vec<u8> {
 /// # Returns
 /// Cryptographically hashed bit sequence
 pub fn hash_randomness(bits: &[u8]) -> Vec<u8> {
+    hash_randomness_with(bits, HashAlgo::Sha3_256)
+}
+
+/// Cryptographically hash the random bits with a chosen hash function
+/// The SHA-3 variants return their fixed digest size; BLAKE3 squeezes its
+/// extendable output to the input length, so hashing does not collapse the
+/// output to 256 bits
+///
+/// # Arguments
+/// * `bits` - Input bit sequence
+/// * `algo` - Hash function to use
+///
+/// # Returns
+/// Cryptographically hashed bit sequence
+pub fn hash_randomness_with(bits: &[u8], algo: HashAlgo) -> Vec<u8> {
     if bits.is_empty() {
         return Vec::new();
     }
     
     // Convert bits to bytes
-    let bytes = bits_to_bytes(bits);
+    let bytes = bits_to_bytes(bits, BitOrder::default());
     
-    // Hash using SHA-3 (256-bit)
-    let mut hasher = Sha3_256::new();
-    hasher.update(&bytes);
-    let result = hasher.finalize();
+    let result = match algo {
+        HashAlgo::Sha3_256 => Sha3_256::digest(&bytes).to_vec(),
+        HashAlgo::Sha3_512 => Sha3_512::digest(&bytes).to_vec(),
+        HashAlgo::Blake3 => {
+            let mut output = vec![0u8; bits.len().div_ceil(8)];
+            blake3::Hasher::new().update(&bytes).finalize_xof().fill(&mut output);
+            output
+        },
+    };
     
     // Convert hash back to bits
-    let mut result_bits = Vec::with_capacity(result.len() * 8);
-    for byte in result {
-        for i in 0..8 {
-            result_bits.push((byte >> i) & 1);
-        }
+    let mut result_bits = bytes_to_bits(&result, BitOrder::default());
+    if algo == HashAlgo::Blake3 {
+        result_bits.truncate(bits.len());
+    }
+    result_bits
+}
+
+/// Cryptographically hash the random bits using the SHAKE256 extendable-output function
+/// Unlike `hash_randomness` the output is not capped at 256 bits, so the
+/// bit count can be preserved (or deliberately shrunk to the estimated entropy)
+///
+/// # Arguments
+/// * `bits` - Input bit sequence
+/// * `output_len_bits` - Number of output bits to squeeze
+///
+/// # Returns
+/// Cryptographically hashed bit sequence of exactly `output_len_bits` bits
+pub fn hash_randomness_xof(bits: &[u8], output_len_bits: usize) -> Vec<u8> {
+    if bits.is_empty() {
+        return Vec::new();
     }
     
+    // Convert bits to bytes
+    let bytes = bits_to_bytes(bits, BitOrder::default());
+    
+    // Absorb with SHAKE256, then squeeze as many bytes as needed
+    let mut hasher = Shake256::default();
+    sha3::digest::Update::update(&mut hasher, &bytes);
+    let mut reader = hasher.finalize_xof();
+    let mut output = vec![0u8; output_len_bits.div_ceil(8)];
+    reader.read(&mut output);
+    
+    let mut result_bits = bytes_to_bits(&output, BitOrder::default());
+    result_bits.truncate(output_len_bits);
+    result_bits
+}
+
+/// Condition the random bits with HMAC-SHA3-256 under a domain-separation key
+/// Output blocks are HMAC(key, counter || input) for a 32-bit big-endian
+/// counter starting at 1 (the SP 800-108 counter construction), so any output
+/// length can be produced. Different keys give unrelated outputs for the same
+/// input; the key labels the application and is not needed to keep the output
+/// secret.
+///
+/// # Arguments
+/// * `bits` - Input bit sequence
+/// * `key` - Domain-separation key, of any length
+/// * `out_bits` - Number of output bits
+///
+/// # Returns
+/// Conditioned bit sequence of exactly `out_bits` bits
+pub fn hmac_condition(bits: &[u8], key: &[u8], out_bits: usize) -> Vec<u8> {
+    if bits.is_empty() {
+        return Vec::new();
+    }
+    
+    let bytes = bits_to_bytes(bits, BitOrder::default());
+    let output_len = out_bits.div_ceil(8);
+    
+    let mut output = Vec::with_capacity(output_len);
+    let mut counter = 1u32;
+    while output.len() < output_len {
+        let mut mac = Hmac::<Sha3_256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+        mac.update(&counter.to_be_bytes());
+        mac.update(&bytes);
+        output.extend_from_slice(&mac.finalize().into_bytes());
+        counter += 1;
+    }
+    output.truncate(output_len);
+    
+    let mut result_bits = bytes_to_bits(&output, BitOrder::default());
+    result_bits.truncate(out_bits);
     result_bits
 }
 
+/// Lightweight whitening by XOR folding
+/// Each pass splits the stream in half and XORs the halves together. If the
+/// halves are independent with bias e, the output bias is 2e^2, so bias drops
+/// quickly, but every pass halves the bit count and the XOR of correlated
+/// halves gains nothing. Unlike SHA-3 this is not a one-way function and
+/// provides no cryptographic guarantee; it is cheap conditioning only.
+/// An odd trailing bit is dropped at each pass.
+///
+/// # Arguments
+/// * `bits` - Input bit sequence
+/// * `passes` - Number of folding passes
+///
+/// # Returns
+/// Folded bit sequence (`bits.len() / 2^passes` bits)
+pub fn xor_fold(bits: &[u8], passes: usize) -> Vec<u8> {
+    let mut result = bits.to_vec();
+    
+    for _ in 0..passes {
+        if result.len() < 2 {
+            break;
+        }
+        
+        let half = result.len() / 2;
+        result = result[..half].iter()
+            .zip(&result[half..2 * half])
+            .map(|(&a, &b)| a ^ b)
+            .collect();
+    }
+    
+    result
+}
+
+/// XOR a bit sequence with bits from a second entropy pool
+///
+/// If the two inputs are independent, the output is at least as
+/// unpredictable as the better of them. A pool shorter than `bits` is reused
+/// from the start, and the repeated part adds no further entropy.
+///
+/// # Arguments
+/// * `bits` - Freshly collected bits
+/// * `pool` - Non-empty bit sequence to mix in
+///
+/// # Returns
+/// Mixed bit sequence of the same length as `bits`
+pub fn xor_mix(bits: &[u8], pool: &[u8]) -> Vec<u8> {
+    bits.iter()
+        .zip(pool.iter().cycle())
+        .map(|(&a, &b)| a ^ b)
+        .collect()
+}
+
+/// XOR a bit sequence with the same number of bits from the OS CSPRNG
+///
+/// The OS generator (`getrandom` on Linux, `BCryptGenRandom` on Windows) is
+/// independent of the sensor, so by the same argument as `xor_mix` the
+/// output is at least as unpredictable as the OS generator alone, even if
+/// the hardware bits are constant or attacker-controlled.
+///
+/// # Arguments
+/// * `bits` - Conditioned bits
+///
+/// # Returns
+/// Mixed bit sequence of the same length as `bits`, or the OS error if its
+/// generator is unavailable
+pub fn mix_os_rng(bits: &[u8]) -> Result<Vec<u8>, rand::Error> {
+    let mut os_bytes = vec![0u8; bits.len().div_ceil(8)];
+    OsRng.try_fill_bytes(&mut os_bytes)?;
+    Ok(xor_mix(bits, &bytes_to_bits(&os_bytes, BitOrder::default())))
+}
+
+/// Order in which bits are packed into a byte
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum BitOrder {
+    /// First bit goes to the least significant position (the historical default)
+    #[default]
+    LsbFirst,
+    /// First bit goes to the most significant position, as most external tools expect
+    MsbFirst,
+}
+
+impl BitOrder {
+    /// Shift of the `index`-th bit (0-7) within a byte
+    fn shift(self, index: usize) -> usize {
+        match self {
+            BitOrder::LsbFirst => index,
+            BitOrder::MsbFirst => 7 - index,
+        }
+    }
+}
+
+/// Deterministically stretch a seed with SHA3-256 in counter mode
+/// Output block i is SHA3-256(seed || i) with i as a big-endian 64-bit
+/// counter. The result is only as unpredictable as the seed: it is a seeded
+/// CSPRNG stream, not raw quantum randomness.
+///
+/// # Arguments
+/// * `seed` - High-entropy seed bytes
+/// * `n` - Number of output bits
+///
+/// # Returns
+/// `n` pseudo-random bits
+pub fn csprng_expand(seed: &[u8], n: usize) -> Vec<u8> {
+    let mut bits = Vec::with_capacity(n + 256);
+    let mut counter: u64 = 0;
+    
+    while bits.len() < n {
+        let mut hasher = Sha3_256::new();
+        hasher.update(seed);
+        hasher.update(counter.to_be_bytes());
+        bits.extend(bytes_to_bits(&hasher.finalize(), BitOrder::default()));
+        counter += 1;
+    }
+    
+    bits.truncate(n);
+    bits
+}
+
+/// Map bits to uniform integers in `[min, max]` by rejection sampling
+/// Each candidate takes the fewest bits (most significant first) that can
+/// cover the range and is rejected if it falls outside it, so unlike reducing
+/// modulo the range no value is favoured. At most half the candidates are
+/// rejected on average.
+///
+/// # Arguments
+/// * `bits` - Conditioned input bits
+/// * `min` - Smallest value (inclusive)
+/// * `max` - Largest value (inclusive), at least `min`
+/// * `count` - Number of integers wanted
+///
+/// # Returns
+/// Up to `count` integers, fewer if the bits run out
+pub fn bits_to_integers(bits: &[u8], min: i64, max: i64, count: usize) -> Vec<i64> {
+    if max < min {
+        return Vec::new();
+    }
+    // Full i64 range spans 2^64 values, so work in u128
+    let range = (max as i128 - min as i128 + 1) as u128;
+    let width = (u128::BITS - (range - 1).leading_zeros()) as usize;
+    if width == 0 {
+        return vec![min; count];
+    }
+    
+    bits.chunks_exact(width)
+        .map(|candidate| candidate.iter().fold(0u128, |acc, &bit| (acc << 1) | bit as u128))
+        .filter(|&value| value < range)
+        .map(|value| (min as i128 + value as i128) as i64)
+        .take(count)
+        .collect()
+}
+
 /// Convert bit vector to byte vector
 ///
 /// # Arguments
 /// * `bits` - Input bit sequence
+/// * `order` - Packing order of bits within each byte
 ///
 /// # Returns
 /// Byte sequence
-pub fn bits_to_bytes(bits: &[u8]) -> Vec<u8> {
+pub fn bits_to_bytes(bits: &[u8], order: BitOrder) -> Vec<u8> {
     // Pad to multiple of 8 if needed
     let padded_len = if bits.len() % 8 != 0 {
         bits.len() + (8 - bits.len() % 8)
@@ -92,7 +640,7 @@ pub fn bits_to_bytes(bits: &[u8]) -> Vec<u8> {
         let mut byte = 0u8;
         for (i, &bit) in chunk.iter().enumerate() {
             if bit == 1 {
-                byte |= 1 << i;
+                byte |= 1 << order.shift(i);
             }
         }
         bytes.push(byte);
@@ -105,15 +653,16 @@ pub fn bits_to_bytes(bits: &[u8]) -> Vec<u8> {
 ///
 /// # Arguments
 /// * `bytes` - Input byte sequence
+/// * `order` - Order in which each byte's bits are emitted (inverse of `bits_to_bytes`)
 ///
 /// # Returns
 /// Bit sequence
-pub fn bytes_to_bits(bytes: &[u8]) -> Vec<u8> {
+pub fn bytes_to_bits(bytes: &[u8], order: BitOrder) -> Vec<u8> {
     let mut bits = Vec::with_capacity(bytes.len() * 8);
     
     for &byte in bytes {
         for i in 0..8 {
-            bits.push((byte >> i) & 1);
+            bits.push((byte >> order.shift(i)) & 1);
         }
     }
     
@@ -152,55 +701,1483 @@ pub fn estimate_entropy(bits: &[u8]) -> f64 {
     entropy
 }
 
-/// Simple statistical tests for randomness quality
+/// Shannon entropy of overlapping n-bit patterns, normalized to bits per bit
+/// Catches structure that single-bit frequencies miss: 0101... has a
+/// per-bit entropy of 1.0 but only two distinct 8-bit patterns
 ///
 /// # Arguments
 /// * `bits` - Input bit sequence
+/// * `n` - Pattern length in bits (clamped to 1-20)
 ///
 /// # Returns
-/// String with test results
-pub fn quick_randomness_test(bits: &[u8]) -> String {
-    if bits.len() < 100 {
-        return "Insufficient bits for testing (need at least 100)".to_string();
+/// Block entropy divided by `n` (0.0-1.0, where 1.0 is perfect)
+pub fn estimate_entropy_ngram(bits: &[u8], n: usize) -> f64 {
+    let n = n.clamp(1, 20);
+    if bits.len() < n {
+        return 0.0;
+    }
+    
+    // Slide an n-bit window over the input, counting each pattern
+    let mask = (1usize << n) - 1;
+    let mut counts = vec![0usize; 1 << n];
+    let mut pattern = 0usize;
+    for (i, &bit) in bits.iter().enumerate() {
+        pattern = ((pattern << 1) | bit as usize) & mask;
+        if i + 1 >= n {
+            counts[pattern] += 1;
+        }
+    }
+    
+    let total = (bits.len() - n + 1) as f64;
+    let entropy: f64 = counts.iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / total;
+            -p * p.log2()
+        })
+        .sum();
+    
+    entropy / n as f64
+}
+
+/// Pattern length used for the n-gram entropy of a report on `len` bits
+///
+/// The longest length up to `NGRAM_REPORT_MAX_SIZE` with enough samples per pattern.
+pub fn default_ngram_size(len: usize) -> usize {
+    (1..=NGRAM_REPORT_MAX_SIZE)
+        .rev()
+        .find(|&n| len >= NGRAM_SAMPLES_PER_PATTERN << n)
+        .unwrap_or(1)
+}
+
+/// Min-entropy estimation per bit
+/// Unlike Shannon entropy this bounds the guessing probability of the
+/// most likely value, which is what cryptographic conditioning relies on
+///
+/// # Arguments
+/// * `bits` - Input bit sequence
+///
+/// # Returns
+/// Estimated min-entropy, -log2(max(p0, p1)) (0.0-1.0, where 1.0 is perfect)
+pub fn estimate_min_entropy(bits: &[u8]) -> f64 {
+    if bits.is_empty() {
+        return 0.0;
     }
     
-    // Count zeros and ones
     let ones = bits.iter().filter(|&&b| b == 1).count();
-    let zeros = bits.len() - ones;
+    let p_max = ones.max(bits.len() - ones) as f64 / bits.len() as f64;
     
-    // Calculate statistics
-    let mean = ones as f64 / bits.len() as f64;
-    let ideal_mean = 0.5;
-    let bias = (mean - ideal_mean).abs();
+    -p_max.log2()
+}
+
+/// NIST SP 800-90B most common value (MCV) min-entropy estimate
+/// Groups the input into `block_size`-bit symbols, takes the upper 99%
+/// confidence bound on the probability of the most common symbol and
+/// converts it to min-entropy
+///
+/// # Arguments
+/// * `bits` - Input bit sequence
+/// * `block_size` - Number of bits per symbol (1 treats the input as binary samples)
+///
+/// # Returns
+/// Estimated min-entropy per bit (0.0-1.0)
+pub fn most_common_value_estimate(bits: &[u8], block_size: usize) -> f64 {
+    let block_size = block_size.clamp(1, 16);
+    let symbols = bits.len() / block_size;
+    if symbols < 2 {
+        return 0.0;
+    }
     
-    // Count transitions (0->1 and 1->0)
-    let mut transitions = 0;
-    for i in 1..bits.len() {
-        if bits[i] != bits[i-1] {
-            transitions += 1;
-        }
+    let mut counts = vec![0usize; 1 << block_size];
+    for block in bits.chunks_exact(block_size) {
+        let symbol = block.iter().fold(0usize, |acc, &bit| (acc << 1) | bit as usize);
+        counts[symbol] += 1;
     }
-    let transition_rate = transitions as f64 / (bits.len() - 1) as f64;
     
-    // Entropy calculation
-    let entropy = estimate_entropy(bits);
+    let max_count = counts.iter().copied().max().unwrap_or(0);
+    let p_hat = max_count as f64 / symbols as f64;
+    let p_upper = (p_hat + 2.576 * (p_hat * (1.0 - p_hat) / (symbols - 1) as f64).sqrt()).min(1.0);
     
-    // Format results
-    format!(
-        "Randomness Test Results:\n\
-        - Bit count: {} ({}% ones, {}% zeros)\n\
-        - Bias from ideal: {:.4}% (ideal: 0%)\n\
-        - Bit transition rate: {:.4} (ideal: 0.5)\n\
-        - Shannon entropy: {:.4} bits/bit (ideal: 1.0)\n\
-        - Quality assessment: {}",
-        bits.len(),
-        (ones * 100) / bits.len(),
-        (zeros * 100) / bits.len(),
-        bias * 100.0,
-        transition_rate,
-        entropy,
-        if bias < 0.05 && entropy > 0.95 { "Good" } 
-        else if bias < 0.1 && entropy > 0.9 { "Fair" }
-        else { "Poor - Consider debiasing" }
-    )
-}
\ No newline at end of file
+    -p_upper.log2() / block_size as f64
+}
+
+/// Complementary error function
+///
+/// Chebyshev approximation with fractional error below 1.2e-7 everywhere,
+/// which is ample precision for p-values compared against 0.01.
+pub fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let ans = t * (-z * z - 1.26551223
+        + t * (1.00002368
+        + t * (0.37409196
+        + t * (0.09678418
+        + t * (-0.18628806
+        + t * (0.27886807
+        + t * (-1.13520398
+        + t * (1.48851587
+        + t * (-0.82215223
+        + t * 0.17087277)))))))))
+        .exp();
+
+    if x >= 0.0 { ans } else { 2.0 - ans }
+}
+
+/// NIST SP 800-22 frequency (monobit) test
+///
+/// # Arguments
+/// * `bits` - Input bit sequence
+///
+/// # Returns
+/// P-value of the test (values below 0.01 indicate non-randomness)
+pub fn monobit_frequency_test(bits: &[u8]) -> f64 {
+    if bits.is_empty() {
+        return 0.0;
+    }
+
+    let n = bits.len() as f64;
+    let sum: i64 = bits.iter().map(|&b| if b == 1 { 1 } else { -1 }).sum();
+    let s_obs = (sum as f64).abs() / n.sqrt();
+
+    erfc(s_obs / std::f64::consts::SQRT_2)
+}
+
+/// NIST SP 800-22 frequency test within a block
+/// Splits the sequence into `block_size`-bit blocks and checks that the
+/// proportion of ones in each is close to 1/2, catching sequences that are
+/// balanced overall but not locally (e.g. all ones followed by all zeros)
+///
+/// # Arguments
+/// * `bits` - Input bit sequence
+/// * `block_size` - Bits per block (see `default_block_size`)
+///
+/// # Returns
+/// P-value of the test (values below 0.01 indicate non-randomness)
+pub fn block_frequency_test(bits: &[u8], block_size: usize) -> f64 {
+    let block_size = block_size.max(1);
+    let blocks = bits.len() / block_size;
+    if blocks == 0 {
+        return 0.0;
+    }
+    
+    let chi_square: f64 = bits.chunks_exact(block_size)
+        .map(|block| {
+            let proportion = block.iter().filter(|&&b| b == 1).count() as f64 / block_size as f64;
+            (proportion - 0.5).powi(2)
+        })
+        .sum::<f64>() * 4.0 * block_size as f64;
+    
+    igamc(blocks as f64 / 2.0, chi_square / 2.0)
+}
+
+/// Proportion of ones in each whole block of the sequence
+///
+/// # Arguments
+/// * `bits` - Input bit sequence
+/// * `block_size` - Bits per block; a trailing partial block is skipped
+///
+/// # Returns
+/// Ones proportion of every block, in order
+pub fn block_ones_proportions(bits: &[u8], block_size: usize) -> Vec<f64> {
+    let block_size = block_size.max(1);
+    bits.chunks_exact(block_size)
+        .map(|block| block.iter().filter(|&&b| b == 1).count() as f64 / block_size as f64)
+        .collect()
+}
+
+/// Block size for `block_frequency_test` following the NIST recommendations
+/// (M >= 20, M > 0.01n and fewer than 100 blocks)
+pub fn default_block_size(len: usize) -> usize {
+    (len / 99 + 1).max(20)
+}
+
+/// Shortest input accepted by `longest_run_test`
+pub const LONGEST_RUN_MIN_BITS: usize = 128;
+
+/// Block size, run-length classes and class probabilities of the longest-run
+/// test for an input length (NIST SP 800-22 section 2.4.4)
+///
+/// Runs at or below the first class count toward it and runs at or above the
+/// last class count toward that one.
+fn longest_run_parameters(len: usize) -> (usize, &'static [usize], &'static [f64]) {
+    if len >= 750_000 {
+        (10_000, &[10, 11, 12, 13, 14, 15, 16], &[0.0882, 0.2092, 0.2483, 0.1933, 0.1208, 0.0675, 0.0727])
+    } else if len >= 6272 {
+        (128, &[4, 5, 6, 7, 8, 9], &[0.1174, 0.2430, 0.2493, 0.1752, 0.1027, 0.1124])
+    } else {
+        (8, &[1, 2, 3, 4], &[0.2148, 0.3672, 0.2305, 0.1875])
+    }
+}
+
+/// NIST SP 800-22 test for the longest run of ones in a block
+/// Splits the sequence into blocks (8, 128 or 10,000 bits depending on its
+/// length), bins the longest run of ones in each block and compares the bins
+/// with their expected distribution, catching clustering of ones or zeros
+///
+/// # Arguments
+/// * `bits` - Input bit sequence (at least `LONGEST_RUN_MIN_BITS` bits)
+///
+/// # Returns
+/// P-value of the test (values below 0.01 indicate non-randomness)
+pub fn longest_run_test(bits: &[u8]) -> f64 {
+    if bits.len() < LONGEST_RUN_MIN_BITS {
+        return 0.0;
+    }
+    
+    let (block_size, classes, probabilities) = longest_run_parameters(bits.len());
+    let mut counts = vec![0usize; classes.len()];
+    for block in bits.chunks_exact(block_size) {
+        let mut longest = 0;
+        let mut run = 0;
+        for &bit in block {
+            run = if bit == 1 { run + 1 } else { 0 };
+            longest = longest.max(run);
+        }
+        
+        let class = classes.iter()
+            .position(|&class| longest <= class)
+            .unwrap_or(classes.len() - 1);
+        counts[class] += 1;
+    }
+    
+    let blocks = (bits.len() / block_size) as f64;
+    let chi_square: f64 = counts.iter()
+        .zip(probabilities)
+        .map(|(&count, &p)| (count as f64 - blocks * p).powi(2) / (blocks * p))
+        .sum();
+    
+    igamc((classes.len() - 1) as f64 / 2.0, chi_square / 2.0)
+}
+
+/// Shortest input for which `spectral_test` is reported (the NIST recommendation)
+pub const SPECTRAL_MIN_BITS: usize = 1000;
+
+/// NIST SP 800-22 discrete Fourier transform (spectral) test
+/// Maps the bits to +/-1, takes the FFT and checks that 95% of the peak
+/// magnitudes in the first half of the spectrum stay below the threshold
+/// expected of random data, detecting periodic features that time-domain
+/// tests miss
+///
+/// # Arguments
+/// * `bits` - Input bit sequence (at least `SPECTRAL_MIN_BITS` recommended)
+///
+/// # Returns
+/// P-value of the test (values below 0.01 indicate non-randomness)
+#[cfg(feature = "spectral")]
+pub fn spectral_test(bits: &[u8]) -> f64 {
+    use rustfft::{num_complex::Complex, FftPlanner};
+    
+    if bits.len() < 2 {
+        return 0.0;
+    }
+    
+    let n = bits.len();
+    let mut spectrum: Vec<Complex<f64>> = bits.iter()
+        .map(|&bit| Complex::new(if bit == 1 { 1.0 } else { -1.0 }, 0.0))
+        .collect();
+    FftPlanner::new().plan_fft_forward(n).process(&mut spectrum);
+    
+    // 95% of the peaks of a random sequence fall below the threshold
+    let threshold = ((1.0f64 / 0.05).ln() * n as f64).sqrt();
+    let expected_below = 0.95 * n as f64 / 2.0;
+    let below = spectrum[..n / 2].iter().filter(|value| value.norm() < threshold).count() as f64;
+    
+    let d = (below - expected_below) / (n as f64 * 0.95 * 0.05 / 4.0).sqrt();
+    erfc(d.abs() / std::f64::consts::SQRT_2)
+}
+
+/// NIST SP 800-22 runs test
+///
+/// # Arguments
+/// * `bits` - Input bit sequence
+///
+/// # Returns
+/// P-value of the test (values below 0.01 indicate non-randomness)
+pub fn runs_test(bits: &[u8]) -> f64 {
+    if bits.len() < 2 {
+        return 0.0;
+    }
+
+    let n = bits.len() as f64;
+    let ones = bits.iter().filter(|&&b| b == 1).count();
+    let pi = ones as f64 / n;
+
+    // Frequency prerequisite: the runs test is meaningless on a heavily biased sequence
+    let tau = 2.0 / n.sqrt();
+    if (pi - 0.5).abs() >= tau {
+        return 0.0;
+    }
+
+    let transitions = bits.windows(2).filter(|w| w[0] != w[1]).count();
+    let v_obs = (transitions + 1) as f64;
+
+    let expected = 2.0 * n * pi * (1.0 - pi);
+    let scale = 2.0 * (2.0 * n).sqrt() * pi * (1.0 - pi);
+
+    erfc((v_obs - expected).abs() / scale)
+}
+
+/// Compression ratio below which output is flagged as structured
+pub const COMPRESSION_RATIO_FLOOR: f64 = 0.9;
+
+/// Packed bytes needed before the compression ratio is trusted (deflate overhead dominates below)
+pub const COMPRESSION_MIN_BYTES: usize = 256;
+
+/// Compressed size over original size of the packed bits, using fast deflate
+/// A smoke test for structure, not a formal statistical test: random data
+/// does not compress (ratio slightly above 1.0 from framing), while a ratio
+/// well below 1.0 means the deflate matcher found repeated patterns or skewed
+/// byte frequencies. Passing says little, since deflate misses most subtle
+/// defects that the NIST tests catch.
+///
+/// # Arguments
+/// * `bits` - Input bit sequence
+///
+/// # Returns
+/// Ratio of compressed to packed length (1.0 for empty input)
+pub fn compression_ratio(bits: &[u8]) -> f64 {
+    use std::io::Write;
+    
+    let bytes = bits_to_bytes(bits, BitOrder::default());
+    if bytes.is_empty() {
+        return 1.0;
+    }
+    
+    let mut encoder = DeflateEncoder::new(Vec::with_capacity(bytes.len()), Compression::fast());
+    let compressed = encoder.write_all(&bytes).and_then(|_| encoder.finish());
+    match compressed {
+        Ok(compressed) => compressed.len() as f64 / bytes.len() as f64,
+        // Writing to memory does not fail; report "incompressible" if it ever does
+        Err(_) => 1.0,
+    }
+}
+
+/// Minimum sequence length for Maurer's universal test (NIST SP 800-22 section 2.9.7, L = 6)
+pub const MAURER_MIN_BITS: usize = 387_840;
+
+/// Smallest sequence length for each block length L = 6..=16 of Maurer's universal test
+const MAURER_BLOCK_THRESHOLDS: [usize; 11] = [
+    387_840, 904_960, 2_068_480, 4_654_080, 10_342_400, 22_753_280,
+    49_643_520, 107_560_960, 231_669_760, 496_435_200, 1_059_061_760,
+];
+
+/// Expected value and variance of the Maurer test statistic for L = 1..=16
+const MAURER_EXPECTED: [(f64, f64); 16] = [
+    (0.7326495, 0.690), (1.5374383, 1.338), (2.4016068, 1.901), (3.3112247, 2.358),
+    (4.2534266, 2.705), (5.2177052, 2.954), (6.1962507, 3.125), (7.1836656, 3.238),
+    (8.1764248, 3.311), (9.1723243, 3.356), (10.170032, 3.384), (11.168765, 3.401),
+    (12.168070, 3.410), (13.167693, 3.416), (14.167488, 3.419), (15.167379, 3.421),
+];
+
+/// Maurer test statistic fn for block length `l` and `q` initialization blocks
+///
+/// # Returns
+/// The statistic and the number K of test blocks, or `None` if `l` is outside
+/// the reference table or the sequence has no test blocks
+fn maurer_statistic(bits: &[u8], l: usize, q: usize) -> Option<(f64, usize)> {
+    let blocks = bits.len() / l.max(1);
+    if l == 0 || l > MAURER_EXPECTED.len() || blocks <= q {
+        return None;
+    }
+    let k = blocks - q;
+    
+    let block_value = |index: usize| {
+        bits[index * l..(index + 1) * l].iter().fold(0usize, |value, &bit| (value << 1) | bit as usize)
+    };
+    
+    // Initialization segment: last (1-based) position of every pattern
+    let mut last_seen = vec![0usize; 1 << l];
+    for index in 0..q {
+        last_seen[block_value(index)] = index + 1;
+    }
+    
+    // Test segment: sum the log2 distance back to each pattern's previous occurrence
+    let mut sum = 0.0;
+    for index in q..blocks {
+        let value = block_value(index);
+        sum += ((index + 1 - last_seen[value]) as f64).log2();
+        last_seen[value] = index + 1;
+    }
+    
+    Some((sum / k as f64, k))
+}
+
+/// Maurer's universal test with block length `l` and `q` initialization blocks
+fn maurer_universal_with(bits: &[u8], l: usize, q: usize) -> f64 {
+    let Some((statistic, k)) = maurer_statistic(bits, l, q) else {
+        return 0.0;
+    };
+    
+    let (expected, variance) = MAURER_EXPECTED[l - 1];
+    let l_f = l as f64;
+    let c = 0.7 - 0.8 / l_f + (4.0 + 32.0 / l_f) * (k as f64).powf(-3.0 / l_f) / 15.0;
+    let sigma = c * (variance / k as f64).sqrt();
+    
+    erfc((statistic - expected).abs() / (std::f64::consts::SQRT_2 * sigma))
+}
+
+/// NIST SP 800-22 Maurer's universal statistical test
+/// Splits the sequence into L-bit blocks, uses the first Q = 10 * 2^L blocks
+/// to initialize a table of last occurrences, then averages the log2 gap
+/// between repeats of each block over the remaining K blocks. Sequences that
+/// could be significantly compressed repeat patterns too soon or too late.
+/// L is chosen from the reference table for the sequence length (6 to 16).
+///
+/// # Arguments
+/// * `bits` - Input bit sequence (at least `MAURER_MIN_BITS` bits)
+///
+/// # Returns
+/// P-value of the test (values below 0.01 indicate non-randomness)
+pub fn maurer_universal_test(bits: &[u8]) -> f64 {
+    let Some(position) = MAURER_BLOCK_THRESHOLDS.iter().rposition(|&threshold| bits.len() >= threshold) else {
+        return 0.0;
+    };
+    let l = 6 + position;
+    
+    maurer_universal_with(bits, l, 10 << l)
+}
+
+/// Minimum sequence length for the cumulative sums test (NIST SP 800-22 section 2.13.7)
+pub const CUSUM_MIN_BITS: usize = 100;
+
+/// Standard normal cumulative distribution function
+fn normal_cdf(x: f64) -> f64 {
+    0.5 * erfc(-x / std::f64::consts::SQRT_2)
+}
+
+/// P-value for a random walk of `n` steps whose largest excursion from zero is `z`
+fn cusum_p_value(n: usize, z: i64) -> f64 {
+    if z == 0 {
+        return 0.0;
+    }
+    
+    let n_f = n as f64;
+    let z_f = z as f64;
+    let sqrt_n = n_f.sqrt();
+    
+    // Integer truncation of the summation bounds matches the NIST reference code
+    let mut sum1 = 0.0;
+    for k in ((-n_f / z_f + 1.0) / 4.0) as i64..=((n_f / z_f - 1.0) / 4.0) as i64 {
+        let k = k as f64;
+        sum1 += normal_cdf((4.0 * k + 1.0) * z_f / sqrt_n) - normal_cdf((4.0 * k - 1.0) * z_f / sqrt_n);
+    }
+    let mut sum2 = 0.0;
+    for k in ((-n_f / z_f - 3.0) / 4.0) as i64..=((n_f / z_f - 1.0) / 4.0) as i64 {
+        let k = k as f64;
+        sum2 += normal_cdf((4.0 * k + 3.0) * z_f / sqrt_n) - normal_cdf((4.0 * k + 1.0) * z_f / sqrt_n);
+    }
+    
+    (1.0 - sum1 + sum2).clamp(0.0, 1.0)
+}
+
+/// NIST SP 800-22 cumulative sums (cusum) test
+/// Maps the bits to +1/-1 and checks whether the running sum strays too far
+/// from zero, either early on (forward) or late (backward), catching slow
+/// bias drift such as a sensor that is still warming up
+///
+/// # Arguments
+/// * `bits` - Input bit sequence (at least `CUSUM_MIN_BITS` bits)
+///
+/// # Returns
+/// Forward and backward p-values (values below 0.01 indicate non-randomness)
+pub fn cusum_test(bits: &[u8]) -> (f64, f64) {
+    if bits.len() < CUSUM_MIN_BITS {
+        return (0.0, 0.0);
+    }
+    
+    let max_excursion = |steps: &mut dyn Iterator<Item = &u8>| {
+        let mut sum = 0i64;
+        let mut max = 0i64;
+        for &bit in steps {
+            sum += if bit == 1 { 1 } else { -1 };
+            max = max.max(sum.abs());
+        }
+        max
+    };
+    
+    let forward = max_excursion(&mut bits.iter());
+    let backward = max_excursion(&mut bits.iter().rev());
+    
+    (cusum_p_value(bits.len(), forward), cusum_p_value(bits.len(), backward))
+}
+
+/// Minimum sequence length for the approximate entropy test in the report battery
+pub const APEN_MIN_BITS: usize = 256;
+
+/// Block length `m` for `approximate_entropy_test`
+///
+/// NIST SP 800-22 section 2.12.7 requires m < floor(log2 n) - 5; the result is
+/// capped at 10, the length NIST uses for million-bit sequences.
+pub fn default_apen_block_size(len: usize) -> usize {
+    (len.max(1).ilog2() as usize).saturating_sub(6).clamp(1, 10)
+}
+
+/// NIST SP 800-22 approximate entropy test
+/// Compares the frequencies of all overlapping m-bit and (m+1)-bit patterns
+/// (wrapping around the end); a sequence more regular than chance, such as a
+/// repeating pattern, gains less information per extra bit than expected
+///
+/// # Arguments
+/// * `bits` - Input bit sequence
+/// * `m` - Pattern length (1 to 20), see `default_apen_block_size`
+///
+/// # Returns
+/// P-value (values below 0.01 indicate non-randomness)
+pub fn approximate_entropy_test(bits: &[u8], m: usize) -> f64 {
+    let n = bits.len();
+    if n <= m || !(1..=20).contains(&m) {
+        return 0.0;
+    }
+    
+    // Sum of p ln p over the circular overlapping patterns of `len` bits
+    let phi = |len: usize| -> f64 {
+        let mask = (1usize << len) - 1;
+        let mut counts = vec![0usize; 1 << len];
+        let mut pattern = bits[..len - 1].iter().fold(0usize, |acc, &bit| (acc << 1) | bit as usize);
+        for i in 0..n {
+            pattern = ((pattern << 1) | bits[(i + len - 1) % n] as usize) & mask;
+            counts[pattern] += 1;
+        }
+        counts.iter()
+            .filter(|&&count| count > 0)
+            .map(|&count| {
+                let p = count as f64 / n as f64;
+                p * p.ln()
+            })
+            .sum()
+    };
+    
+    let apen = phi(m) - phi(m + 1);
+    let chi_square = 2.0 * n as f64 * (std::f64::consts::LN_2 - apen);
+    igamc((1usize << (m - 1)) as f64, chi_square / 2.0)
+}
+
+/// Block length used for the linear complexity test in the report battery
+pub const LINEAR_COMPLEXITY_BLOCK_SIZE: usize = 500;
+
+/// Minimum sequence length for the linear complexity test in the report battery
+/// (200 blocks, the fewest NIST SP 800-22 section 2.10.7 recommends)
+pub const LINEAR_COMPLEXITY_MIN_BITS: usize = 200 * LINEAR_COMPLEXITY_BLOCK_SIZE;
+
+/// Probabilities of the seven deviation classes of the linear complexity test
+const LINEAR_COMPLEXITY_PROBABILITIES: [f64; 7] = [0.010417, 0.03125, 0.125, 0.5, 0.25, 0.0625, 0.020833];
+
+/// Length of the shortest LFSR generating `bits` (Berlekamp-Massey algorithm)
+///
+/// # Arguments
+/// * `bits` - Input bit sequence
+///
+/// # Returns
+/// Linear complexity, from 0 (all zeros) to `bits.len()`
+pub fn berlekamp_massey(bits: &[u8]) -> usize {
+    let n = bits.len();
+    // Connection polynomial and its copy from before the last length change
+    let mut c = vec![0u8; n + 1];
+    let mut b = vec![0u8; n + 1];
+    c[0] = 1;
+    b[0] = 1;
+    let mut l = 0;
+    let mut last_change: isize = -1;
+    
+    for i in 0..n {
+        let discrepancy = (1..=l).fold(bits[i], |d, j| d ^ (c[j] & bits[i - j]));
+        if discrepancy == 1 {
+            let previous = c.clone();
+            let shift = (i as isize - last_change) as usize;
+            for j in 0..=n - shift {
+                c[j + shift] ^= b[j];
+            }
+            if l <= i / 2 {
+                l = i + 1 - l;
+                last_change = i as isize;
+                b = previous;
+            }
+        }
+    }
+    
+    l
+}
+
+/// NIST SP 800-22 linear complexity test
+/// Computes the linear complexity of every block with Berlekamp-Massey and
+/// compares how far each falls from its expected value with the theoretical
+/// distribution, catching LFSR-like structure and sources that alias to a
+/// short cycle
+///
+/// # Arguments
+/// * `bits` - Input bit sequence
+/// * `block_size` - Bits per block (NIST recommends 500 to 5000)
+///
+/// # Returns
+/// P-value (values below 0.01 indicate non-randomness)
+pub fn linear_complexity_test(bits: &[u8], block_size: usize) -> f64 {
+    let blocks = bits.len() / block_size.max(1);
+    if block_size == 0 || blocks == 0 {
+        return 0.0;
+    }
+    
+    let m = block_size as f64;
+    let sign = if block_size % 2 == 0 { 1.0 } else { -1.0 };
+    let expected = m / 2.0 + (9.0 - sign) / 36.0 - (m / 3.0 + 2.0 / 9.0) / 2f64.powf(m);
+    
+    let mut counts = [0usize; 7];
+    for block in bits.chunks_exact(block_size) {
+        let t = sign * (berlekamp_massey(block) as f64 - expected) + 2.0 / 9.0;
+        let class = match t {
+            t if t <= -2.5 => 0,
+            t if t <= -1.5 => 1,
+            t if t <= -0.5 => 2,
+            t if t <= 0.5 => 3,
+            t if t <= 1.5 => 4,
+            t if t <= 2.5 => 5,
+            _ => 6,
+        };
+        counts[class] += 1;
+    }
+    
+    let chi_square: f64 = counts.iter()
+        .zip(LINEAR_COMPLEXITY_PROBABILITIES)
+        .map(|(&count, p)| {
+            let expected_count = blocks as f64 * p;
+            (count as f64 - expected_count).powi(2) / expected_count
+        })
+        .sum();
+    
+    igamc(3.0, chi_square / 2.0)
+}
+
+/// Natural logarithm of the gamma function (Lanczos approximation)
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.18009172947146, -86.50532032941677, 24.01409824083091,
+        -1.231739572450155, 0.1208650973866179e-2, -0.5395239384953e-5,
+    ];
+    
+    let tmp = x + 5.5;
+    let tmp = tmp - (x + 0.5) * tmp.ln();
+    let mut series = 1.000000000190015;
+    for (i, c) in COEFFICIENTS.iter().enumerate() {
+        series += c / (x + 1.0 + i as f64);
+    }
+    
+    -tmp + (2.5066282746310005 * series / x).ln()
+}
+
+/// Regularized upper incomplete gamma function Q(a, x)
+///
+/// Uses the series expansion below x = a + 1 and the continued fraction
+/// above it, as in Numerical Recipes.
+pub fn igamc(a: f64, x: f64) -> f64 {
+    if x <= 0.0 || a <= 0.0 {
+        return 1.0;
+    }
+    
+    let ln_prefix = -x + a * x.ln() - ln_gamma(a);
+    
+    if x < a + 1.0 {
+        // Series for P(a, x)
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        let mut ap = a;
+        for _ in 0..1000 {
+            ap += 1.0;
+            term *= x / ap;
+            sum += term;
+            if term.abs() < sum.abs() * 1e-15 {
+                break;
+            }
+        }
+        (1.0 - sum * ln_prefix.exp()).max(0.0)
+    } else {
+        // Lentz's method for the continued fraction of Q(a, x)
+        let tiny = 1e-300;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / tiny;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for i in 1..1000 {
+            let an = -(i as f64) * (i as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < tiny { d = tiny; }
+            c = b + an / c;
+            if c.abs() < tiny { c = tiny; }
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < 1e-15 {
+                break;
+            }
+        }
+        ln_prefix.exp() * h
+    }
+}
+
+/// Chi-square goodness-of-fit test of byte values against a uniform distribution
+/// Packs the bits into bytes and compares the 256-bin histogram with the
+/// expected count of n/256 per value, catching byte-level structure that
+/// bit-level tests miss
+///
+/// # Arguments
+/// * `bits` - Input bit sequence
+///
+/// # Returns
+/// Tuple of (chi-square statistic, p-value with 255 degrees of freedom)
+pub fn chi_square_byte_test(bits: &[u8]) -> (f64, f64) {
+    let bytes = bits_to_bytes(&bits[..bits.len() / 8 * 8], BitOrder::default());
+    if bytes.is_empty() {
+        return (0.0, 0.0);
+    }
+    
+    let mut histogram = [0usize; 256];
+    for &byte in &bytes {
+        histogram[byte as usize] += 1;
+    }
+    
+    let expected = bytes.len() as f64 / 256.0;
+    let statistic: f64 = histogram.iter()
+        .map(|&count| {
+            let diff = count as f64 - expected;
+            diff * diff / expected
+        })
+        .sum();
+    
+    (statistic, igamc(255.0 / 2.0, statistic / 2.0))
+}
+
+/// Normalized autocorrelation of the sequence with itself shifted by `lag`
+/// Bits are mapped to +1/-1, so the result is 0 for an uncorrelated
+/// sequence, +1 for one that repeats with this period and -1 for one that
+/// alternates with it
+///
+/// # Arguments
+/// * `bits` - Input bit sequence
+/// * `lag` - Shift in bits
+///
+/// # Returns
+/// Autocorrelation coefficient (-1.0 to 1.0)
+pub fn autocorrelation_test(bits: &[u8], lag: usize) -> f64 {
+    if lag == 0 || bits.len() <= lag {
+        return 0.0;
+    }
+    
+    let agreements = bits.iter()
+        .zip(&bits[lag..])
+        .filter(|(a, b)| a == b)
+        .count();
+    let pairs = bits.len() - lag;
+    
+    (2.0 * agreements as f64 - pairs as f64) / pairs as f64
+}
+
+/// Lag-1 serial correlation coefficient
+/// Pearson correlation between the sequence and its shift-by-one copy
+///
+/// # Arguments
+/// * `bits` - Input bit sequence
+///
+/// # Returns
+/// Correlation coefficient (-1.0 to 1.0, ideal: 0.0); 0.0 if undefined
+pub fn serial_correlation(bits: &[u8]) -> f64 {
+    if bits.len() < 3 {
+        return 0.0;
+    }
+    
+    let x = &bits[..bits.len() - 1];
+    let y = &bits[1..];
+    let n = x.len() as f64;
+    
+    let mean_x = x.iter().map(|&b| b as f64).sum::<f64>() / n;
+    let mean_y = y.iter().map(|&b| b as f64).sum::<f64>() / n;
+    
+    let mut covariance = 0.0;
+    let mut variance_x = 0.0;
+    let mut variance_y = 0.0;
+    for (&a, &b) in x.iter().zip(y) {
+        let dx = a as f64 - mean_x;
+        let dy = b as f64 - mean_y;
+        covariance += dx * dy;
+        variance_x += dx * dx;
+        variance_y += dy * dy;
+    }
+    
+    // A constant sequence has no defined correlation
+    if variance_x == 0.0 || variance_y == 0.0 {
+        return 0.0;
+    }
+    
+    covariance / (variance_x * variance_y).sqrt()
+}
+
+/// Find the lag in 1..=`max_lag` with the largest absolute autocorrelation
+/// Rolling-shutter sensors and mains hum show up as a spike at a
+/// characteristic lag
+///
+/// # Arguments
+/// * `bits` - Input bit sequence
+/// * `max_lag` - Largest lag to scan
+///
+/// # Returns
+/// Tuple of (lag, autocorrelation at that lag), or (0, 0.0) if no lag fits
+pub fn worst_autocorrelation(bits: &[u8], max_lag: usize) -> (usize, f64) {
+    (1..=max_lag.min(bits.len().saturating_sub(1)))
+        .map(|lag| (lag, autocorrelation_test(bits, lag)))
+        .fold((0, 0.0), |worst, current| if current.1.abs() > worst.1.abs() { current } else { worst })
+}
+
+/// Whether an autocorrelation is significant after correcting for scanning `max_lag` lags
+///
+/// Under randomness the coefficient at a lag is approximately normal with
+/// variance 1/(n - lag); the significance level is split across all lags.
+pub fn autocorrelation_significant(bits: &[u8], lag: usize, correlation: f64, max_lag: usize) -> bool {
+    if lag == 0 || bits.len() <= lag {
+        return false;
+    }
+    
+    let z = correlation.abs() * ((bits.len() - lag) as f64).sqrt();
+    erfc(z / std::f64::consts::SQRT_2) < NIST_SIGNIFICANCE / max_lag.max(1) as f64
+}
+
+/// NIST SP 800-90B repetition count health test
+/// Fails if any bit value repeats for at least the cutoff
+/// C = 1 + ceil(20 / H) consecutive samples, which catches a source
+/// that has become stuck on a constant output
+///
+/// # Arguments
+/// * `bits` - Input bit sequence
+///
+/// # Returns
+/// True if the sequence passes
+pub fn repetition_count_test(bits: &[u8]) -> bool {
+    let cutoff = 1 + (HEALTH_ALPHA_EXPONENT / HEALTH_ASSUMED_MIN_ENTROPY).ceil() as usize;
+    let mut run = 1;
+    
+    for pair in bits.windows(2) {
+        if pair[0] == pair[1] {
+            run += 1;
+            if run >= cutoff {
+                return false;
+            }
+        } else {
+            run = 1;
+        }
+    }
+    
+    true
+}
+
+/// NIST SP 800-90B adaptive proportion health test
+/// Splits the input into windows of `APT_WINDOW_SIZE` bits (or one shorter
+/// window) and fails if the first bit of any window occurs too often in it,
+/// which catches a large loss of entropy such as a heavily biased source
+///
+/// # Arguments
+/// * `bits` - Input bit sequence
+///
+/// # Returns
+/// True if the sequence passes
+pub fn adaptive_proportion_test(bits: &[u8]) -> bool {
+    let window_size = bits.len().min(APT_WINDOW_SIZE);
+    if window_size < 2 {
+        return true;
+    }
+    
+    let probability = 2f64.powf(-HEALTH_ASSUMED_MIN_ENTROPY);
+    let alpha = 2f64.powf(-HEALTH_ALPHA_EXPONENT);
+    let cutoff = 1 + binomial_critical_value(window_size, probability, alpha);
+    
+    bits.chunks_exact(window_size).all(|window| {
+        let first = window[0];
+        window.iter().filter(|&&b| b == first).count() < cutoff
+    })
+}
+
+/// Smallest k such that P(X <= k) >= 1 - alpha for X ~ Binomial(n, p)
+fn binomial_critical_value(n: usize, p: f64, alpha: f64) -> usize {
+    let (ln_p, ln_q) = (p.ln(), (1.0 - p).ln());
+    let mut ln_choose = 0.0;
+    let mut cdf = 0.0;
+    
+    for k in 0..=n {
+        if k > 0 {
+            ln_choose += ((n - k + 1) as f64).ln() - (k as f64).ln();
+        }
+        // Work in log space: individual terms underflow for large n
+        cdf += (ln_choose + k as f64 * ln_p + (n - k) as f64 * ln_q).exp();
+        if cdf >= 1.0 - alpha {
+            return k;
+        }
+    }
+    
+    n
+}
+
+/// Bit counts through each post-processing stage, for judging source efficiency
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EntropyYield {
+    /// Raw bits collected from the source
+    pub raw_bits: usize,
+    /// Bits left after debiasing, if an extractor was applied
+    pub debiased_bits: Option<usize>,
+    /// Bits left after whitening or hashing, if applied
+    pub whitened_bits: Option<usize>,
+    /// Estimated min-entropy of the raw input in bits per bit
+    pub raw_min_entropy: f64,
+}
+
+impl EntropyYield {
+    /// Number of bits in the final output
+    pub fn output_bits(&self) -> usize {
+        self.whitened_bits.or(self.debiased_bits).unwrap_or(self.raw_bits)
+    }
+    
+    /// Estimated total min-entropy of the raw input in bits
+    pub fn raw_entropy_bits(&self) -> f64 {
+        self.raw_bits as f64 * self.raw_min_entropy
+    }
+    
+    /// Fraction of raw bits that survived to the output
+    pub fn efficiency(&self) -> f64 {
+        self.output_bits() as f64 / self.raw_bits.max(1) as f64
+    }
+}
+
+/// Overall quality verdict of a randomness report, best first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Quality {
+    Good,
+    Fair,
+    Poor,
+}
+
+impl Quality {
+    /// Whether this verdict is at least as good as `minimum`
+    pub fn meets(self, minimum: Quality) -> bool {
+        self <= minimum
+    }
+    
+    /// Human-readable verdict used in text reports
+    pub fn description(&self) -> &'static str {
+        match self {
+            Quality::Good => "Good",
+            Quality::Fair => "Fair",
+            Quality::Poor => "Poor - Consider debiasing",
+        }
+    }
+}
+
+/// Machine-readable summary of the statistical quality of a bit sequence
+#[derive(Debug, Clone, Serialize)]
+pub struct RandomnessReport {
+    pub bit_count: usize,
+    pub ones: usize,
+    pub zeros: usize,
+    /// Absolute deviation of the ones proportion from 0.5
+    pub bias: f64,
+    /// Fraction of adjacent bit pairs that differ (ideal: 0.5)
+    pub transition_rate: f64,
+    /// Lag-1 serial correlation coefficient (ideal: 0.0)
+    pub serial_correlation: f64,
+    /// Shannon entropy in bits per bit (ideal: 1.0)
+    pub shannon_entropy: f64,
+    /// Min-entropy in bits per bit (ideal: 1.0)
+    pub min_entropy: f64,
+    /// SP 800-90B most common value min-entropy estimate in bits per bit
+    pub mcv_min_entropy: f64,
+    /// Pattern length used for `ngram_entropy`
+    pub ngram_size: usize,
+    /// Shannon entropy of overlapping `ngram_size`-bit patterns in bits per bit (ideal: 1.0)
+    pub ngram_entropy: f64,
+    /// NIST SP 800-22 test outcomes
+    pub tests: Vec<TestResult>,
+    /// Chi-square statistic of byte values against a uniform distribution
+    pub byte_chi_square: f64,
+    /// P-value of `byte_chi_square` (255 degrees of freedom)
+    pub byte_chi_square_p_value: f64,
+    /// Lag with the strongest autocorrelation (1 to `AUTOCORRELATION_MAX_LAG`)
+    pub worst_autocorrelation_lag: usize,
+    /// Autocorrelation coefficient at `worst_autocorrelation_lag`
+    pub worst_autocorrelation: f64,
+    /// Whether the worst autocorrelation indicates periodic structure
+    pub periodic: bool,
+    /// Deflate compressed size over packed size (a heuristic; ideal: about 1.0)
+    pub compression_ratio: f64,
+    pub quality: Quality,
+}
+
+impl RandomnessReport {
+    /// Whether every pass/fail test in the report passed
+    ///
+    /// Covers the NIST tests, the byte chi-square test and the
+    /// autocorrelation scan; entropy estimates are informational.
+    pub fn all_passed(&self) -> bool {
+        self.tests.iter().all(|test| test.passed)
+            && self.byte_chi_square_p_value >= NIST_SIGNIFICANCE
+            && !self.periodic
+    }
+}
+
+/// Compute the statistics behind `quick_randomness_test`
+///
+/// # Arguments
+/// * `bits` - Input bit sequence
+///
+/// # Returns
+/// Report with bit counts, bias, transition rate, entropy and test results
+pub fn randomness_report(bits: &[u8]) -> RandomnessReport {
+    // Count zeros and ones
+    let ones = bits.iter().filter(|&&b| b == 1).count();
+    let zeros = bits.len() - ones;
+    
+    // Calculate statistics
+    let mean = ones as f64 / bits.len().max(1) as f64;
+    let ideal_mean = 0.5;
+    let bias = (mean - ideal_mean).abs();
+    
+    // Count transitions (0->1 and 1->0)
+    let transitions = bits.windows(2).filter(|w| w[0] != w[1]).count();
+    let transition_rate = transitions as f64 / bits.len().saturating_sub(1).max(1) as f64;
+    
+    // Entropy calculation
+    let entropy = estimate_entropy(bits);
+    let ngram_size = default_ngram_size(bits.len());
+    let ngram_entropy = estimate_entropy_ngram(bits, ngram_size);
+    
+    // NIST SP 800-22 tests
+    let mut tests = vec![
+        TestResult::new("Frequency (monobit)", monobit_frequency_test(bits)),
+        TestResult::new("Block frequency", block_frequency_test(bits, default_block_size(bits.len()))),
+        TestResult::new("Runs", runs_test(bits)),
+    ];
+    if bits.len() >= LONGEST_RUN_MIN_BITS {
+        tests.push(TestResult::new("Longest run of ones", longest_run_test(bits)));
+    }
+    if bits.len() >= MAURER_MIN_BITS {
+        tests.push(TestResult::new("Maurer universal", maurer_universal_test(bits)));
+    }
+    if bits.len() >= LINEAR_COMPLEXITY_MIN_BITS {
+        tests.push(TestResult::new("Linear complexity", linear_complexity_test(bits, LINEAR_COMPLEXITY_BLOCK_SIZE)));
+    }
+    if bits.len() >= APEN_MIN_BITS {
+        tests.push(TestResult::new("Approximate entropy", approximate_entropy_test(bits, default_apen_block_size(bits.len()))));
+    }
+    if bits.len() >= CUSUM_MIN_BITS {
+        let (forward, backward) = cusum_test(bits);
+        tests.push(TestResult::new("Cumulative sums (forward)", forward));
+        tests.push(TestResult::new("Cumulative sums (backward)", backward));
+    }
+    #[cfg(feature = "spectral")]
+    if bits.len() >= SPECTRAL_MIN_BITS {
+        tests.push(TestResult::new("Spectral (DFT)", spectral_test(bits)));
+    }
+    
+    // Byte-level uniformity
+    let (byte_chi_square, byte_chi_square_p_value) = chi_square_byte_test(bits);
+    
+    // Periodic structure
+    let (worst_autocorrelation_lag, worst_autocorrelation) = worst_autocorrelation(bits, AUTOCORRELATION_MAX_LAG);
+    let periodic = autocorrelation_significant(bits, worst_autocorrelation_lag, worst_autocorrelation, AUTOCORRELATION_MAX_LAG);
+    
+    // Local imbalance can hide behind a balanced global count
+    let block_structure = tests.iter().any(|test| test.name == "Block frequency" && !test.passed);
+    
+    // Cheap compressibility smoke test
+    let compression_ratio = compression_ratio(bits);
+    let compressible = bits.len() / 8 >= COMPRESSION_MIN_BYTES && compression_ratio < COMPRESSION_RATIO_FLOOR;
+    
+    let quality = if periodic || block_structure || compressible || ngram_entropy < NGRAM_ENTROPY_FLOOR { Quality::Poor }
+        else if bias < 0.05 && entropy > 0.95 { Quality::Good }
+        else if bias < 0.1 && entropy > 0.9 { Quality::Fair }
+        else { Quality::Poor };
+    
+    RandomnessReport {
+        bit_count: bits.len(),
+        ones,
+        zeros,
+        bias,
+        transition_rate,
+        serial_correlation: serial_correlation(bits),
+        shannon_entropy: entropy,
+        min_entropy: estimate_min_entropy(bits),
+        mcv_min_entropy: most_common_value_estimate(bits, MCV_REPORT_BLOCK_SIZE),
+        ngram_size,
+        ngram_entropy,
+        tests,
+        byte_chi_square,
+        byte_chi_square_p_value,
+        worst_autocorrelation_lag,
+        worst_autocorrelation,
+        periodic,
+        compression_ratio,
+        quality,
+    }
+}
+
+/// Simple statistical tests for randomness quality
+///
+/// # Arguments
+/// * `bits` - Input bit sequence
+///
+/// # Returns
+/// String with test results
+pub fn quick_randomness_test(bits: &[u8]) -> String {
+    if bits.len() < 100 {
+        return "Insufficient bits for testing (need at least 100)".to_string();
+    }
+    
+    let report = randomness_report(bits);
+    
+    // Format results
+    let mut result = format!(
+        "Randomness Test Results:\n\
+        - Bit count: {} ({}% ones, {}% zeros)\n\
+        - Bias from ideal: {:.4}% (ideal: 0%)\n\
+        - Bit transition rate: {:.4} (ideal: 0.5)\n\
+        - Serial correlation: {:.4} (ideal: 0.0)\n\
+        - Shannon entropy: {:.4} bits/bit (ideal: 1.0)\n\
+        - Min-entropy: {:.4} bits/bit (MCV estimate: {:.4}, ideal: 1.0)\n\
+        - {}-gram entropy: {:.4} bits/bit (ideal: 1.0)\n",
+        report.bit_count,
+        (report.ones * 100) / report.bit_count,
+        (report.zeros * 100) / report.bit_count,
+        report.bias * 100.0,
+        report.transition_rate,
+        report.serial_correlation,
+        report.shannon_entropy,
+        report.min_entropy,
+        report.mcv_min_entropy,
+        report.ngram_size,
+        report.ngram_entropy,
+    );
+    
+    for test in &report.tests {
+        result.push_str(&format!(
+            "- NIST {}: p = {:.4} ({})\n",
+            test.name,
+            test.p_value,
+            if test.passed { "pass" } else { "FAIL" },
+        ));
+    }
+    
+    result.push_str(&format!(
+        "- Byte chi-square: {:.2} (p = {:.4}, {})\n",
+        report.byte_chi_square,
+        report.byte_chi_square_p_value,
+        if report.byte_chi_square_p_value >= NIST_SIGNIFICANCE { "pass" } else { "FAIL" },
+    ));
+    
+    result.push_str(&format!(
+        "- Worst autocorrelation: {:.4} at lag {} ({})\n",
+        report.worst_autocorrelation,
+        report.worst_autocorrelation_lag,
+        if report.periodic { "periodic structure detected" } else { "ok" },
+    ));
+    
+    result.push_str(&format!(
+        "- Compression ratio: {:.4} (heuristic, ideal: about 1.0{})\n",
+        report.compression_ratio,
+        if report.bit_count / 8 >= COMPRESSION_MIN_BYTES && report.compression_ratio < COMPRESSION_RATIO_FLOOR { ", compressible" } else { "" },
+    ));
+    
+    result.push_str(&format!("- Quality assessment: {}", report.quality.description()));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Bit sequence written as a string of '0' and '1' characters
+    fn bits(s: &str) -> Vec<u8> {
+        s.bytes().map(|c| c - b'0').collect()
+    }
+    
+    /// Deterministic pseudo-random bits, about `ones_percent` percent of them ones (SplitMix64)
+    fn biased_bits(n: usize, ones_percent: u64, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..n)
+            .map(|_| {
+                state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+                let mut z = state;
+                z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+                z ^= z >> 31;
+                (z % 100 < ones_percent) as u8
+            })
+            .collect()
+    }
+    
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-6, "expected {expected}, got {actual}");
+    }
+    
+    #[test]
+    fn monobit_matches_nist_example() {
+        // NIST SP 800-22 section 2.1.8
+        assert_close(monobit_frequency_test(&bits("1011010101")), 0.527089);
+    }
+    
+    #[test]
+    fn runs_matches_nist_example() {
+        // NIST SP 800-22 section 2.3.8
+        assert_close(runs_test(&bits("1001101011")), 0.147232);
+    }
+    
+    #[test]
+    fn berlekamp_massey_finds_known_complexities() {
+        assert_eq!(berlekamp_massey(&[0; 20]), 0);
+        assert_eq!(berlekamp_massey(&[1; 20]), 1);
+        assert_eq!(berlekamp_massey(&bits(&"10".repeat(10))), 2);
+        // NIST SP 800-22 section 2.10.8
+        assert_eq!(berlekamp_massey(&bits("1101011110001")), 4);
+    }
+    
+    #[test]
+    fn linear_complexity_rejects_lfsr_output() {
+        // s[i] = s[i - 3] ^ s[i - 4] (x^4 + x + 1): every block has linear complexity 4
+        let mut lfsr = vec![1, 0, 0, 1];
+        while lfsr.len() < LINEAR_COMPLEXITY_MIN_BITS {
+            let next = lfsr[lfsr.len() - 3] ^ lfsr[lfsr.len() - 4];
+            lfsr.push(next);
+        }
+        assert!(linear_complexity_test(&lfsr, LINEAR_COMPLEXITY_BLOCK_SIZE) < NIST_SIGNIFICANCE);
+    }
+    
+    #[test]
+    fn maurer_matches_nist_example() {
+        // NIST SP 800-22 section 2.9.8: L = 2, Q = 4, so K = 6 test blocks
+        let (statistic, k) = maurer_statistic(&bits("01011010011101010111"), 2, 4).unwrap();
+        assert_eq!(k, 6);
+        assert_close(statistic, 1.1949875);
+        
+        // The worked example divides by sqrt(variance) alone; step (5) of section 2.9.4,
+        // which maurer_universal_with follows, also scales sigma by c / sqrt(K)
+        let (expected, variance) = MAURER_EXPECTED[1];
+        let p_value = erfc((statistic - expected).abs() / (std::f64::consts::SQRT_2 * variance.sqrt()));
+        assert_close(p_value, 0.767189);
+    }
+    
+    #[test]
+    fn maurer_rejects_all_zeros() {
+        assert!(maurer_universal_test(&vec![0; MAURER_MIN_BITS]) < NIST_SIGNIFICANCE);
+    }
+    
+    #[test]
+    fn approximate_entropy_matches_nist_example() {
+        // NIST SP 800-22 section 2.12.8
+        assert_close(approximate_entropy_test(&bits("0100110101"), 3), 0.261961);
+    }
+    
+    #[test]
+    fn approximate_entropy_rejects_repeating_patterns() {
+        for pattern in ["01", "0011"] {
+            let repeating = bits(&pattern.repeat(1024 / pattern.len()));
+            let m = default_apen_block_size(repeating.len());
+            assert!(approximate_entropy_test(&repeating, m) < NIST_SIGNIFICANCE, "pattern {pattern}");
+        }
+    }
+    
+    #[test]
+    fn cusum_matches_nist_example() {
+        // NIST SP 800-22 section 2.13.8: the forward walk of 1011010111 peaks at z = 4.
+        // The example is shorter than CUSUM_MIN_BITS, so the p-value is checked directly
+        assert_close(cusum_p_value(10, 4), 0.4116588);
+    }
+    
+    #[test]
+    fn cusum_rejects_drift_toward_ones() {
+        // Three ones in every four bits
+        let (forward, backward) = cusum_test(&bits(&"1110".repeat(250)));
+        assert!(forward < NIST_SIGNIFICANCE, "forward {forward}");
+        assert!(backward < NIST_SIGNIFICANCE, "backward {backward}");
+    }
+    
+    #[test]
+    fn longest_run_matches_nist_example() {
+        // NIST SP 800-22 section 2.4.8. The published p-value, 0.180609, is off in the
+        // fifth decimal: igamc(3/2, 4.882605/2) for the example's chi-square is 0.180598
+        let epsilon = bits(concat!(
+            "11001100000101010110110001001100111000000000001001001101010100010001",
+            "001111010110100000001101011111001100111001101101100010110010",
+        ));
+        assert_eq!(epsilon.len(), 128);
+        let p_value = longest_run_test(&epsilon);
+        assert!((p_value - 0.180609).abs() < 2e-5, "got {p_value}");
+        assert_close(p_value, 0.180598);
+    }
+    
+    #[test]
+    fn longest_run_rejects_long_runs() {
+        // Every 8-bit block holds a run of seven ones
+        assert!(longest_run_test(&bits(&"11111110".repeat(128))) < NIST_SIGNIFICANCE);
+        assert!(longest_run_test(&[1; 1024]) < NIST_SIGNIFICANCE);
+    }
+    
+    #[cfg(feature = "spectral")]
+    #[test]
+    fn spectral_rejects_periodic_pattern() {
+        // Sign of a sine wave with a period of 20 bits
+        let periodic: Vec<u8> = (0..4096)
+            .map(|i| ((i as f64 * std::f64::consts::TAU / 20.0).sin() > 0.0) as u8)
+            .collect();
+        assert!(spectral_test(&periodic) < NIST_SIGNIFICANCE);
+    }
+    
+    #[test]
+    fn autocorrelation_flags_period() {
+        let periodic = bits(&"1001101".repeat(1000));
+        let (lag, correlation) = worst_autocorrelation(&periodic, AUTOCORRELATION_MAX_LAG);
+        assert_eq!(lag, 7);
+        assert_close(correlation, 1.0);
+        assert!(autocorrelation_significant(&periodic, lag, correlation, AUTOCORRELATION_MAX_LAG));
+    }
+    
+    #[test]
+    fn autocorrelation_ignores_random_bits() {
+        let random = biased_bits(10_000, 50, 1);
+        let (lag, correlation) = worst_autocorrelation(&random, AUTOCORRELATION_MAX_LAG);
+        assert!(!autocorrelation_significant(&random, lag, correlation, AUTOCORRELATION_MAX_LAG), "lag {lag}: {correlation}");
+    }
+    
+    #[test]
+    fn serial_correlation_of_alternating_bits_is_minus_one() {
+        assert_close(serial_correlation(&bits(&"01".repeat(500))), -1.0);
+    }
+    
+    #[test]
+    fn serial_correlation_of_random_bits_is_near_zero() {
+        let correlation = serial_correlation(&biased_bits(10_000, 50, 2));
+        assert!(correlation.abs() < 0.05, "got {correlation}");
+    }
+    
+    #[test]
+    fn serial_correlation_is_zero_when_undefined() {
+        assert_eq!(serial_correlation(&[]), 0.0);
+        assert_eq!(serial_correlation(&[0; 100]), 0.0);
+        assert_eq!(serial_correlation(&[1; 100]), 0.0);
+    }
+    
+    #[test]
+    fn chi_square_byte_rejects_skewed_bytes() {
+        // Nine in ten bytes are zero
+        let skewed: Vec<u8> = (0..4096u32).map(|i| if i % 10 == 0 { i as u8 } else { 0 }).collect();
+        let (statistic, p_value) = chi_square_byte_test(&bytes_to_bits(&skewed, BitOrder::default()));
+        assert!(statistic > 1000.0, "statistic {statistic}");
+        assert!(p_value < 1e-10, "p-value {p_value}");
+    }
+    
+    #[test]
+    fn chi_square_byte_accepts_uniform_cycle() {
+        let uniform: Vec<u8> = (0..16).flat_map(|_| 0..=255).collect();
+        let (statistic, p_value) = chi_square_byte_test(&bytes_to_bits(&uniform, BitOrder::default()));
+        assert_eq!(statistic, 0.0);
+        assert_close(p_value, 1.0);
+    }
+    
+    #[test]
+    fn bit_packing_round_trips_in_both_orders() {
+        let original = biased_bits(1000, 50, 3);
+        for order in [BitOrder::LsbFirst, BitOrder::MsbFirst] {
+            assert_eq!(bytes_to_bits(&bits_to_bytes(&original, order), order), original, "{order:?}");
+        }
+    }
+    
+    #[test]
+    fn bit_orders_put_the_first_bit_at_opposite_ends() {
+        assert_eq!(bytes_to_bits(&[0x01], BitOrder::LsbFirst), [1, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(bytes_to_bits(&[0x01], BitOrder::MsbFirst), [0, 0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(bits_to_bytes(&[1, 0, 0, 0, 0, 0, 0, 0], BitOrder::LsbFirst), [0x01]);
+        assert_eq!(bits_to_bytes(&[0, 0, 0, 0, 0, 0, 0, 1], BitOrder::MsbFirst), [0x01]);
+    }
+    
+    #[test]
+    fn trailing_partial_byte_is_zero_padded() {
+        assert_eq!(bits_to_bytes(&[1, 1, 0], BitOrder::LsbFirst), [0x03]);
+        assert_eq!(bits_to_bytes(&[1, 1, 0], BitOrder::MsbFirst), [0xC0]);
+        
+        // A whole byte followed by the same three bits
+        let partial = bits("00000001110");
+        assert_eq!(bits_to_bytes(&partial, BitOrder::LsbFirst), [0x80, 0x03]);
+        assert_eq!(bits_to_bytes(&partial, BitOrder::MsbFirst), [0x01, 0xC0]);
+    }
+    
+    #[test]
+    fn peres_extends_classic_output() {
+        let biased = biased_bits(10_000, 70, 4);
+        let classic = von_neumann_debias(&biased);
+        let peres = von_neumann_debias_peres(&biased);
+        assert!(peres.len() > classic.len(), "Peres {} bits, classic {}", peres.len(), classic.len());
+        // Peres emits the classic output first, then what it recovers from discarded pairs
+        assert!(peres.starts_with(&classic));
+    }
+    
+    #[test]
+    fn elias_yield_grows_past_classic() {
+        let biased = biased_bits(20_000, 70, 5);
+        let classic = von_neumann_debias(&biased);
+        assert_eq!(elias_extract(&biased, 2), classic);
+        
+        // A 3-bit symbol yields one bit for two of its three arrangements with k = 1 or 2:
+        // 2pq bits per 3 input bits, against pq per bit for pairs
+        assert!(elias_extract(&biased, 3).len() < classic.len());
+        for symbol_bits in 4..=ELIAS_MAX_SYMBOL_BITS {
+            let elias = elias_extract(&biased, symbol_bits).len();
+            assert!(elias > classic.len(), "{symbol_bits}-bit symbols: {elias} bits, classic {}", classic.len());
+        }
+    }
+    
+    #[test]
+    fn elias_output_is_balanced() {
+        let debiased = elias_extract(&biased_bits(20_000, 70, 6), ELIAS_DEFAULT_SYMBOL_BITS);
+        let ones = debiased.iter().filter(|&&bit| bit == 1).count() as f64 / debiased.len() as f64;
+        assert!((ones - 0.5).abs() < 0.02, "ones proportion {ones}");
+    }
+    
+    #[test]
+    fn toeplitz_output_length_follows_entropy_budget() {
+        let input = biased_bits(4096, 50, 7);
+        for min_entropy in [0.5f64, 0.777, 1.0] {
+            let expected = (4096.0 * min_entropy).floor() as usize - 2 * TOEPLITZ_SECURITY_BITS;
+            assert_eq!(toeplitz_extract(&input, min_entropy).len(), expected, "min-entropy {min_entropy}");
+        }
+    }
+    
+    #[test]
+    fn toeplitz_output_is_empty_without_security_margin() {
+        let input = biased_bits(1000, 50, 8);
+        // Budgets of 100 and 128 bits leave nothing after the 128-bit margin
+        assert!(toeplitz_extract(&input, 0.1).is_empty());
+        assert!(toeplitz_extract(&input, 0.128).is_empty());
+        assert!(toeplitz_extract(&[], 1.0).is_empty());
+    }
+    
+    #[test]
+    fn toeplitz_is_linear_over_gf2() {
+        let a = biased_bits(1024, 50, 9);
+        let b = biased_bits(1024, 50, 10);
+        let sum: Vec<u8> = a.iter().zip(&b).map(|(x, y)| x ^ y).collect();
+        let expected: Vec<u8> = toeplitz_extract(&a, 0.5).iter().zip(toeplitz_extract(&b, 0.5)).map(|(x, y)| x ^ y).collect();
+        assert_eq!(toeplitz_extract(&sum, 0.5), expected);
+    }
+    
+    #[test]
+    fn chunked_von_neumann_matches_whole_input() {
+        // Odd length, and enough unequal pairs that the whole-input fallback bit never applies
+        let input = biased_bits(101, 60, 11);
+        let whole = von_neumann_debias(&input);
+        assert!(!whole.is_empty());
+        
+        for split in 0..=input.len() {
+            let mut debiaser = VonNeumannDebiaser::new();
+            let mut chunked = debiaser.push_chunk(&input[..split]);
+            chunked.extend(debiaser.push_chunk(&input[split..]));
+            assert_eq!(chunked, whole, "split at {split}");
+            assert_eq!(debiaser.finish(), Some(input[100]), "split at {split}");
+        }
+        
+        let mut debiaser = VonNeumannDebiaser::new();
+        let bitwise: Vec<u8> = input.chunks(1).flat_map(|bit| debiaser.push_chunk(bit)).collect();
+        assert_eq!(bitwise, whole);
+    }
+}
@@ -0,0 +1,197 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::error::{QrngError, Result};
+use crate::{QrngSource, UnitsRead};
+
+/// Time spent waiting on reads that delivered nothing
+#[derive(Debug, Default)]
+struct StallClock {
+    /// Total length of the empty reads since the last bit arrived
+    empty_reads: Duration,
+    /// Start of the read in progress, if any
+    read_started: Option<Instant>,
+}
+
+/// Progress of a watched source, shared with threads monitoring it
+#[derive(Debug)]
+pub struct StallState {
+    clock: Mutex<StallClock>,
+    bits: AtomicUsize,
+    finished: AtomicBool,
+}
+
+impl StallState {
+    /// Time spent inside reads since the source last delivered a bit
+    ///
+    /// Only reads count, so time the caller spends processing or writing
+    /// between reads is never mistaken for a stalled device.
+    pub fn stalled_for(&self) -> Duration {
+        self.clock.lock()
+            .map(|clock| clock.empty_reads + clock.read_started.map(|start| start.elapsed()).unwrap_or_default())
+            .unwrap_or_default()
+    }
+
+    /// Bits delivered so far
+    pub fn bits(&self) -> usize {
+        self.bits.load(Ordering::Relaxed)
+    }
+
+    /// Whether the watchdog was dropped, so the source is no longer read
+    pub fn finished(&self) -> bool {
+        self.finished.load(Ordering::Relaxed)
+    }
+}
+
+/// Source adapter that fails once another source stops delivering bits
+///
+/// Any source can stall: a frozen camera, a microphone unplugged mid-run, a
+/// silent serial line. Reads that keep coming back empty for longer than the
+/// timeout return [`QrngError::Stalled`]. A read that blocks inside the
+/// device driver cannot be interrupted from here; [`StallWatchdog::state`]
+/// lets another thread notice it instead.
+pub struct StallWatchdog<S: QrngSource> {
+    source: S,
+    name: String,
+    timeout: Duration,
+    state: Arc<StallState>,
+}
+
+impl<S: QrngSource> StallWatchdog<S> {
+    /// # Arguments
+    /// * `source` - Source to watch
+    /// * `name` - Description of the source used in the error
+    /// * `timeout` - Longest time without a new bit
+    pub fn new(source: S, name: impl Into<String>, timeout: Duration) -> Self {
+        StallWatchdog {
+            source,
+            name: name.into(),
+            timeout,
+            state: Arc::new(StallState {
+                clock: Mutex::new(StallClock::default()),
+                bits: AtomicUsize::new(0),
+                finished: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    /// Shared progress, for watching reads that never return
+    pub fn state(&self) -> Arc<StallState> {
+        Arc::clone(&self.state)
+    }
+}
+
+impl<S: QrngSource> Drop for StallWatchdog<S> {
+    fn drop(&mut self) {
+        self.state.finished.store(true, Ordering::Relaxed);
+    }
+}
+
+impl<S: QrngSource> QrngSource for StallWatchdog<S> {
+    /// Read a chunk, failing once reads have delivered nothing for longer than the timeout
+    fn read_chunk(&mut self) -> Result<Vec<u8>> {
+        if let Ok(mut clock) = self.state.clock.lock() {
+            clock.read_started = Some(Instant::now());
+        }
+        let result = self.source.read_chunk();
+        if let Ok(mut clock) = self.state.clock.lock() {
+            let elapsed = clock.read_started.take().map(|start| start.elapsed()).unwrap_or_default();
+            match &result {
+                Ok(bits) if !bits.is_empty() => clock.empty_reads = Duration::ZERO,
+                _ => clock.empty_reads += elapsed,
+            }
+        }
+
+        let bits = result?;
+        if !bits.is_empty() {
+            self.state.bits.fetch_add(bits.len(), Ordering::Relaxed);
+        } else if self.state.stalled_for() > self.timeout {
+            return Err(QrngError::Stalled { source_name: self.name.clone(), got: self.state.bits(), timeout: self.timeout });
+        }
+
+        Ok(bits)
+    }
+
+    fn collection_timeout(&self) -> Option<Duration> {
+        self.source.collection_timeout()
+    }
+
+    fn begin_collection(&mut self) {
+        self.source.begin_collection()
+    }
+
+    fn estimated_rate_bits_per_sec(&self) -> Option<f64> {
+        self.source.estimated_rate_bits_per_sec()
+    }
+
+    fn set_raw_capture(&mut self, sink: Box<dyn std::io::Write>) -> bool {
+        self.source.set_raw_capture(sink)
+    }
+
+    fn units_read(&self) -> Option<UnitsRead> {
+        self.source.units_read()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+    use std::thread;
+
+    /// Source that delivers `chunks` non-empty chunks, then nothing
+    struct DryingSource {
+        chunks: usize,
+        delay: Duration,
+    }
+
+    impl QrngSource for DryingSource {
+        fn read_chunk(&mut self) -> Result<Vec<u8>> {
+            thread::sleep(self.delay);
+            if self.chunks == 0 {
+                return Ok(Vec::new());
+            }
+            self.chunks -= 1;
+            Ok(vec![1, 0, 1, 0])
+        }
+    }
+
+    #[test]
+    fn empty_reads_fail_after_timeout() {
+        let source = DryingSource { chunks: 2, delay: Duration::from_millis(5) };
+        let mut watchdog = StallWatchdog::new(source, "drying source", Duration::from_millis(50));
+        let result = watchdog.collect_bits_cancellable(100, &AtomicBool::new(false));
+        match result {
+            Err(QrngError::Stalled { got, .. }) => assert_eq!(got, 8),
+            other => panic!("expected a stall, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn time_between_reads_is_not_a_stall() {
+        let source = DryingSource { chunks: 0, delay: Duration::ZERO };
+        let mut watchdog = StallWatchdog::new(source, "idle source", Duration::from_millis(50));
+        let state = watchdog.state();
+
+        // The caller is busy elsewhere, e.g. processing the bits collected so far
+        thread::sleep(Duration::from_millis(100));
+        assert!(state.stalled_for() < Duration::from_millis(50));
+        assert!(watchdog.read_chunk().unwrap().is_empty());
+    }
+
+    #[test]
+    fn progress_resets_the_clock() {
+        let source = DryingSource { chunks: 1, delay: Duration::from_millis(20) };
+        let mut watchdog = StallWatchdog::new(source, "source", Duration::from_secs(10));
+        let state = watchdog.state();
+        watchdog.read_chunk().unwrap();
+        assert_eq!(state.stalled_for(), Duration::ZERO);
+        assert_eq!(state.bits(), 4);
+
+        watchdog.read_chunk().unwrap();
+        assert!(state.stalled_for() >= Duration::from_millis(20));
+        drop(watchdog);
+        assert!(state.finished());
+    }
+}
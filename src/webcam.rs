@@ -1,64 +1,572 @@
-use anyhow::{Result, anyhow};
 use opencv::{
     prelude::*,
     videoio,
-    core,
     imgproc,
 };
 
-/// Generates random bits using quantum noise from webcam CMOS sensor
+#[cfg(not(feature = "single-threaded"))]
+use rayon::prelude::*;
+use sha3::digest::{ExtendableOutput, Update, XofReader};
+use sha3::Shake256;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::error::{QrngError, Result};
+use crate::{collect_whole_chunks, utils, QrngSource, UnitsRead};
+
+/// Bit mask selecting only bit plane 0 (the least significant bit)
+pub const DEFAULT_BIT_MASK: u8 = 0b0000_0001;
+
+/// Default capture width; the lowest common resolution gives the fastest readout
+pub const DEFAULT_WIDTH: u32 = 160;
+
+/// Default capture height
+pub const DEFAULT_HEIGHT: u32 = 120;
+
+/// Consecutive identical frames after which the camera is considered frozen
+const MAX_CONSECUTIVE_DUPLICATES: usize = 100;
+
+/// Frame rate assumed by `estimated_rate_bits_per_sec`; most webcams deliver 30 fps
+const ASSUMED_FPS: f64 = 30.0;
+
+/// Low bits of the inter-frame delay (in nanoseconds) mixed in by `mix_timing`
+const TIMING_MIX_BITS: usize = 8;
+
+/// Bits squeezed from each frame by default in frame-hash extraction
+pub const DEFAULT_FRAME_HASH_BITS: usize = 256;
+
+/// Which pixel values bits are harvested from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum WebcamChannels {
+    /// The grayscale conversion of each pixel (averages away some per-channel noise)
+    #[default]
+    Gray,
+    /// The blue, green and red values of each pixel separately, about three times the yield
+    ///
+    /// The channels of one pixel share some fixed-pattern noise, so their
+    /// bits are not fully independent; pair with `spatial_diff`.
+    Rgb,
+}
+
+impl WebcamChannels {
+    /// Bytes per pixel of the frame the bits are taken from
+    fn bytes_per_pixel(self) -> usize {
+        match self {
+            WebcamChannels::Gray => 1,
+            WebcamChannels::Rgb => 3,
+        }
+    }
+}
+
+/// How bits are taken from each frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum WebcamExtract {
+    /// The selected bit planes of every pixel
+    #[default]
+    Lsb,
+    /// SHAKE256 of the whole frame, squeezed to a fixed number of bits
+    ///
+    /// Spreads the noise of all pixels over every output bit, so fixed-pattern
+    /// noise and quantization artifacts do not show through. Sound only if
+    /// each frame holds at least that many bits of min-entropy.
+    FrameHash,
+}
+
+/// Camera selection and extraction settings for a webcam source
+#[derive(Debug, Clone)]
+pub struct WebcamConfig {
+    /// OpenCV device index (0 is the system default camera)
+    pub camera_index: i32,
+    /// Requested frame width; the driver may clamp it
+    pub width: u32,
+    /// Requested frame height; the driver may clamp it
+    pub height: u32,
+    /// Pixel bit planes to harvest (bit `n` set selects plane `n`)
+    pub bit_mask: u8,
+    /// XOR the low bits of the inter-frame arrival time into each frame's first bits
+    ///
+    /// Assumes the OS monotonic clock has sub-millisecond resolution; on a
+    /// coarse clock the low bits of the delay are constant and add nothing.
+    pub mix_timing: bool,
+    /// XOR each pixel with its left neighbour before extracting bit planes
+    ///
+    /// Removes the fixed-pattern noise shared by adjacent pixels at the cost
+    /// of one bit per row and bit plane (the first pixel has no neighbour).
+    pub spatial_diff: bool,
+    /// Stop a collection after this many frames even if fewer bits were gathered
+    ///
+    /// Each frame's bit count is logged, which shows the yield of a camera.
+    /// With `frame_xor` every combined frame counts once. The budget is
+    /// enforced in `read_chunk`, so it holds under adapters too, and is reset
+    /// only by `begin_collection`: a collection split into several calls
+    /// (such as a health check followed by the rest) shares one budget.
+    pub max_frames: Option<usize>,
+    /// Pause before every frame read after the first
+    ///
+    /// Cameras that hand back a cached frame when read faster than they
+    /// expose need a pause of about one frame period (33 ms at 30 fps).
+    pub frame_delay: Duration,
+    /// Harvest the grayscale value or each color channel of every pixel
+    pub channels: WebcamChannels,
+    /// Take bit planes of each pixel or a hash of each whole frame
+    pub extract: WebcamExtract,
+    /// Bits squeezed from each frame in frame-hash extraction
+    pub frame_hash_bits: usize,
+    /// Harvest only every `pixel_stride`-th pixel of each frame (1 keeps every pixel)
+    ///
+    /// Neighbouring pixels share readout circuitry, column amplifiers and, on
+    /// color sensors, demosaicing, which correlates their low bits. The count
+    /// runs on across rows, so a stride that does not divide the frame width
+    /// samples different columns on consecutive rows.
+    pub pixel_stride: usize,
+    /// XOR this many consecutive distinct frames byte by byte and extract from the result (1 uses each frame alone)
+    ///
+    /// Static fixed-pattern noise cancels when the count is even, while the
+    /// independent shot noise of each frame survives. Bits arrive `frame_xor`
+    /// times less often; the reads in between return no bits.
+    pub frame_xor: usize,
+}
+
+impl Default for WebcamConfig {
+    fn default() -> Self {
+        WebcamConfig {
+            camera_index: 0,
+            width: DEFAULT_WIDTH,
+            height: DEFAULT_HEIGHT,
+            bit_mask: DEFAULT_BIT_MASK,
+            mix_timing: false,
+            spatial_diff: false,
+            max_frames: None,
+            frame_delay: Duration::ZERO,
+            channels: WebcamChannels::default(),
+            extract: WebcamExtract::default(),
+            frame_hash_bits: DEFAULT_FRAME_HASH_BITS,
+            pixel_stride: 1,
+            frame_xor: 1,
+        }
+    }
+}
+
+/// Webcam CMOS sensor quantum noise source
 ///
-/// # Arguments
-/// * `num_bits` - Number of random bits to generate
+/// The camera is opened on first use and released when the source is dropped.
+/// Bits of the last frame beyond what a collection needs stay buffered for
+/// the next read, so splitting a collection wastes no part of a frame.
+pub struct WebcamSource {
+    config: WebcamConfig,
+    capture: Option<videoio::VideoCapture>,
+    frame: Mat,
+    gray: Mat,
+    last_frame_hash: Option<u64>,
+    duplicate_frames: usize,
+    consecutive_duplicates: usize,
+    last_frame_time: Option<Instant>,
+    reported_selection: bool,
+    frames: usize,
+    /// Frames delivered since the current collection began, for `max_frames`
+    collection_frames: usize,
+    /// Surplus bits of the last frame, served before the next frame is read
+    pending_bits: Vec<u8>,
+    /// XOR of the frames read since the last combined frame
+    xor_frame: Vec<u8>,
+    xor_frames: usize,
+    raw_capture: Option<Box<dyn Write>>,
+}
+
+impl Default for WebcamSource {
+    fn default() -> Self {
+        Self::with_config(WebcamConfig::default())
+    }
+}
+
+impl WebcamSource {
+    /// Source harvesting only the least significant bit of each pixel of the default camera
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// # Arguments
+    /// * `bit_mask` - Pixel bit planes to harvest (bit `n` set selects plane `n`)
+    pub fn with_bit_mask(bit_mask: u8) -> Self {
+        Self::with_config(WebcamConfig { bit_mask, ..WebcamConfig::default() })
+    }
+
+    /// # Arguments
+    /// * `config` - Camera, resolution and bit-plane settings
+    pub fn with_config(config: WebcamConfig) -> Self {
+        WebcamSource {
+            config,
+            capture: None,
+            frame: Mat::default(),
+            gray: Mat::default(),
+            last_frame_hash: None,
+            duplicate_frames: 0,
+            consecutive_duplicates: 0,
+            last_frame_time: None,
+            reported_selection: false,
+            frames: 0,
+            collection_frames: 0,
+            pending_bits: Vec::new(),
+            xor_frame: Vec::new(),
+            xor_frames: 0,
+            raw_capture: None,
+        }
+    }
+
+    /// Number of distinct frames extracted so far
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// Number of frames skipped so far because they repeated the previous frame
+    pub fn duplicate_frames(&self) -> usize {
+        self.duplicate_frames
+    }
+}
+
+/// Extract the given bit planes from each pixel, lowest plane first
+fn extract_bit_planes(pixels: &[u8], planes: &[u8]) -> Vec<u8> {
+    let mut bits = Vec::with_capacity(pixels.len() * planes.len());
+    for &byte in pixels {
+        for &plane in planes {
+            bits.push((byte >> plane) & 1);
+        }
+    }
+    bits
+}
+
+/// XOR each pixel of a row with its left neighbour
 ///
-/// # Returns
-/// Vector of random bits (0s and 1s as u8)
-pub fn webcam_qrng(num_bits: usize) -> Result<Vec<u8>> {
-    let mut cap = videoio::VideoCapture::new(0, videoio::CAP_ANY)?;
-    
+/// `stride` is the number of bytes per pixel, so each color channel is
+/// differenced with the same channel. Yields one pixel fewer than the row.
+fn pixel_diffs(row: &[u8], stride: usize) -> Vec<u8> {
+    row.iter().zip(row.iter().skip(stride)).map(|(left, right)| right ^ left).collect()
+}
+
+/// Keep every `pixel_stride`-th pixel of a row of `stride`-byte pixels
+///
+/// `first` is the frame-wide index of the row's first pixel, so the
+/// selection carries on from the previous row instead of restarting.
+fn subsample_pixels(row: &[u8], stride: usize, pixel_stride: usize, first: usize) -> Vec<u8> {
+    row.chunks(stride)
+        .enumerate()
+        .filter(|(i, _)| (first + i) % pixel_stride == 0)
+        .flat_map(|(_, pixel)| pixel.iter().copied())
+        .collect()
+}
+
+/// Hash a whole frame with SHAKE256 and squeeze `out_bits` bits from it
+///
+/// `frame_delta` (the inter-frame delay in nanoseconds) is hashed in too when given.
+fn hash_frame(bytes: &[u8], frame_delta: Option<u64>, out_bits: usize) -> Vec<u8> {
+    let mut hasher = Shake256::default();
+    hasher.update(bytes);
+    if let Some(delta) = frame_delta {
+        hasher.update(&delta.to_le_bytes());
+    }
+
+    let mut digest = vec![0u8; out_bits.div_ceil(8)];
+    hasher.finalize_xof().read(&mut digest);
+    let mut bits = utils::bytes_to_bits(&digest, utils::BitOrder::default());
+    bits.truncate(out_bits);
+    bits
+}
+
+/// Request a capture property, warning if the driver rejects or clamps it
+fn set_property(cap: &mut videoio::VideoCapture, property: i32, name: &str, value: u32) -> Result<()> {
+    if !cap.set(property, value as f64)? {
+        log::warn!("Camera driver rejected {} = {}", name, value);
+    }
+
+    let actual = cap.get(property)?;
+    if actual.round() as u32 != value {
+        log::warn!("Requested {} {} but the camera is using {}", name, value, actual);
+    }
+
+    Ok(())
+}
+
+/// Open the camera and configure it for noise collection
+fn open_camera(config: &WebcamConfig) -> Result<videoio::VideoCapture> {
+    let mut cap = videoio::VideoCapture::new(config.camera_index, videoio::CAP_ANY)?;
+
     if !cap.is_opened()? {// Check if camera is opened
-        return Err(anyhow!("Failed to open webcam. Ensure it's connected and not in use by another application"));
-    }
-    
-    cap.set(videoio::CAP_PROP_FRAME_WIDTH, 160.0)?;// Set camera to lowest possible resolution for faster readout
-    cap.set(videoio::CAP_PROP_FRAME_HEIGHT, 120.0)?;
-    
-    println!("Collecting quantum noise from webcam CMOS sensor...");//ensure dark conditions
-    println!("For best results: Place webcam in dark container or cover lens");
-    
-    let mut random_bits = Vec::with_capacity(num_bits);
-    let mut frame = Mat::default();
-    let mut gray = Mat::default();
-    let mut last_percent = 0; //progress tracking
-
-    while random_bits.len() < num_bits {
+        return Err(QrngError::NoDevice(format!("Failed to open webcam {}. Ensure it's connected and not in use by another application", config.camera_index)));
+    }
+
+    set_property(&mut cap, videoio::CAP_PROP_FRAME_WIDTH, "width", config.width)?;
+    set_property(&mut cap, videoio::CAP_PROP_FRAME_HEIGHT, "height", config.height)?;
+
+    log::info!("Collecting quantum noise from webcam CMOS sensor...");//ensure dark conditions
+    log::info!("For best results: Place webcam in dark container or cover lens");
+
+    Ok(cap)
+}
+
+impl WebcamSource {
+    /// Read one frame (completing a combined frame with `frame_xor`) and extract the selected bit planes of each pixel
+    fn read_frame(&mut self) -> Result<Vec<u8>> {
+        if self.capture.is_none() {
+            self.capture = Some(open_camera(&self.config)?);
+        }
+        let cap = self.capture.as_mut().expect("camera opened above");
+
+        if !self.config.frame_delay.is_zero() && self.last_frame_time.is_some() {
+            std::thread::sleep(self.config.frame_delay);
+        }
+
         // Read a new frame
-        if !cap.read(&mut frame)? {
+        if !cap.read(&mut self.frame)? {
             std::thread::sleep(std::time::Duration::from_millis(10));
-            continue;
-        }
-        
-        imgproc::cvt_color(&frame, &mut gray, imgproc::COLOR_BGR2GRAY, 0)?; // Convert to grayscale
-        
-        // Extract the least significant bit of each pixel
-        let bytes = gray.data_bytes()?;
-        for &byte in bytes {
-            random_bits.push(byte & 1);
-            if random_bits.len() >= num_bits {
-                break;
+            return Ok(Vec::new());
+        }
+
+        // Capture pipeline jitter: nanoseconds since the previous successful read
+        let now = Instant::now();
+        let frame_delta = self.last_frame_time.map(|last| now.duration_since(last).as_nanos() as u64);
+        self.last_frame_time = Some(now);
+
+        let stride = self.config.channels.bytes_per_pixel();
+        let bytes = match self.config.channels {
+            WebcamChannels::Gray => {
+                imgproc::cvt_color(&self.frame, &mut self.gray, imgproc::COLOR_BGR2GRAY, 0)?; // Convert to grayscale
+                self.gray.data_bytes()?
+            },
+            WebcamChannels::Rgb => {
+                if self.frame.channels() != 3 {
+                    return Err(QrngError::UnsupportedFormat(format!(
+                        "RGB extraction needs 3-channel BGR frames, the camera delivers {} channels", self.frame.channels()
+                    )));
+                }
+                self.frame.data_bytes()?
+            },
+        };
+
+        // A driver replaying a cached frame would re-harvest the same bits
+        let mut hasher = DefaultHasher::new();
+        bytes.hash(&mut hasher);
+        let frame_hash = hasher.finish();
+        if self.last_frame_hash == Some(frame_hash) {
+            self.duplicate_frames += 1;
+            self.consecutive_duplicates += 1;
+            if self.consecutive_duplicates >= MAX_CONSECUTIVE_DUPLICATES {
+                return Err(QrngError::CameraFrozen(self.consecutive_duplicates));
+            }
+            return Ok(Vec::new());
+        }
+        self.last_frame_hash = Some(frame_hash);
+        self.consecutive_duplicates = 0;
+        self.frames += 1;
+
+        if let Some(capture) = self.raw_capture.as_mut() {
+            capture.write_all(bytes)?;
+        }
+
+        // Duplicates were skipped above, since a repeated frame would cancel
+        // to zero along with the fixed-pattern noise
+        let combined;
+        let bytes = if self.config.frame_xor > 1 {
+            if self.xor_frame.len() != bytes.len() {
+                if self.xor_frames > 0 {
+                    log::warn!("Frame size changed from {} to {} bytes; restarting the XOR of {} frames", self.xor_frame.len(), bytes.len(), self.config.frame_xor);
+                }
+                self.xor_frame = vec![0; bytes.len()];
+                self.xor_frames = 0;
+            }
+            for (xored, &byte) in self.xor_frame.iter_mut().zip(bytes) {
+                *xored ^= byte;
+            }
+            self.xor_frames += 1;
+            if self.xor_frames < self.config.frame_xor {
+                return Ok(Vec::new());
+            }
+            self.xor_frames = 0;
+            combined = std::mem::take(&mut self.xor_frame);
+            &combined[..]
+        } else {
+            bytes
+        };
+
+        if self.config.extract == WebcamExtract::FrameHash {
+            let delta = frame_delta.filter(|_| self.config.mix_timing);
+            return Ok(hash_frame(bytes, delta, self.config.frame_hash_bits));
+        }
+
+        // Extract each selected bit plane of each selected pixel, lowest plane
+        // first, processing rows in parallel and concatenating them in frame order
+        let cols = (self.frame.cols() as usize).max(1);
+        let row_len = cols * stride;
+        let planes: Vec<u8> = (0..8).filter(|plane| self.config.bit_mask & (1 << *plane) != 0).collect();
+
+        let spatial_diff = self.config.spatial_diff;
+        let pixel_stride = self.config.pixel_stride.max(1);
+        let extract = |(index, row): (usize, &[u8])| {
+            // Differences are taken between adjacent pixels before any are skipped
+            let diffs;
+            let values = if spatial_diff {
+                diffs = pixel_diffs(row, stride);
+                &diffs[..]
+            } else {
+                row
+            };
+            if pixel_stride > 1 {
+                extract_bit_planes(&subsample_pixels(values, stride, pixel_stride, index * cols), &planes)
+            } else {
+                extract_bit_planes(values, &planes)
+            }
+        };
+
+        #[cfg(not(feature = "single-threaded"))]
+        let rows: Vec<Vec<u8>> = bytes.par_chunks(row_len).enumerate().map(extract).collect();
+        #[cfg(feature = "single-threaded")]
+        let rows: Vec<Vec<u8>> = bytes.chunks(row_len).enumerate().map(extract).collect();
+
+        let mut bits = rows.concat();
+
+        // Show once what the differential and the pixel stride buy on this camera
+        if (spatial_diff || pixel_stride > 1) && !self.reported_selection {
+            self.reported_selection = true;
+            let plain = extract_bit_planes(bytes, &planes);
+            let selection = match (spatial_diff, pixel_stride) {
+                (true, 1) => "Spatial differential".to_string(),
+                (false, _) => format!("Pixel stride {pixel_stride}"),
+                (true, _) => format!("Spatial differential with pixel stride {pixel_stride}"),
+            };
+            log::info!(
+                "{selection}: lag-1 serial correlation {:.4} -> {:.4} on the first frame ({} of {} bits kept)",
+                utils::serial_correlation(&plain), utils::serial_correlation(&bits), bits.len(), plain.len()
+            );
+        }
+
+        // Timing bits only cover the start of the frame, so they protect a
+        // sensor with suppressed pixel noise without repeating across the frame
+        if let (true, Some(delta)) = (self.config.mix_timing, frame_delta) {
+            for (i, bit) in bits.iter_mut().take(TIMING_MIX_BITS).enumerate() {
+                *bit ^= ((delta >> i) & 1) as u8;
             }
         }
-        
-        let percent = (random_bits.len() * 100) / num_bits;
-        if percent > last_percent && percent % 10 == 0 {
-            println!("Progress: {}%", percent);
-            last_percent = percent;
+
+        Ok(bits)
+    }
+}
+
+impl QrngSource for WebcamSource {
+    /// Read one frame, failing with [`QrngError::FrameLimit`] once the
+    /// collection has used up its `max_frames` budget
+    ///
+    /// Bits buffered from the previous frame are returned first, without
+    /// counting as a frame.
+    fn read_chunk(&mut self) -> Result<Vec<u8>> {
+        if !self.pending_bits.is_empty() {
+            return Ok(std::mem::take(&mut self.pending_bits));
+        }
+
+        let Some(max_frames) = self.config.max_frames else {
+            return self.read_frame();
+        };
+        if self.collection_frames >= max_frames {
+            return Err(QrngError::FrameLimit(max_frames));
+        }
+
+        let bits = self.read_frame()?;
+        if !bits.is_empty() {
+            self.collection_frames += 1;
+            log::info!("Frame {}: {} bits", self.collection_frames, bits.len());
         }
+        Ok(bits)
     }
-    
-    println!("Noise collection complete");
-    
-    cap.release()?;// Release camera
-    
-    Ok(random_bits[..num_bits].to_vec())
-}
\ No newline at end of file
+
+    fn begin_collection(&mut self) {
+        self.collection_frames = 0;
+    }
+
+    /// Requested resolution over the pixel stride times the selected bit planes and channels at 30 fps
+    /// over the frames XORed together
+    /// (the squeezed bits per frame with frame-hash extraction)
+    fn estimated_rate_bits_per_sec(&self) -> Option<f64> {
+        let fps = ASSUMED_FPS.min(1.0 / self.config.frame_delay.as_secs_f64()) / self.config.frame_xor.max(1) as f64;
+        if self.config.extract == WebcamExtract::FrameHash {
+            return Some(self.config.frame_hash_bits as f64 * fps);
+        }
+
+        let width = if self.config.spatial_diff { self.config.width.saturating_sub(1) } else { self.config.width };
+        let pixels = width as f64 * self.config.height as f64 / self.config.pixel_stride.max(1) as f64;
+        let bytes = pixels * self.config.channels.bytes_per_pixel() as f64;
+        Some(bytes * self.config.bit_mask.count_ones() as f64 * fps)
+    }
+
+    /// Captures each distinct frame as row-major bytes: `width * height` grayscale
+    /// values, or `width * height * 3` interleaved blue, green, red values in RGB mode
+    fn set_raw_capture(&mut self, sink: Box<dyn Write>) -> bool {
+        self.raw_capture = Some(sink);
+        true
+    }
+
+    /// Distinct frames; skipped duplicates are not counted
+    fn units_read(&self) -> Option<UnitsRead> {
+        Some(UnitsRead { count: self.frames, unit: "frame" })
+    }
+
+    fn collect_bits_cancellable(&mut self, num_bits: usize, cancel: &AtomicBool) -> Result<Vec<u8>> {
+        let mut bits = collect_whole_chunks(self, num_bits, cancel)?;
+        self.pending_bits = bits.split_off(num_bits.min(bits.len()));
+        log::info!("Noise collection complete");
+
+        if self.duplicate_frames > 0 {
+            log::info!("Skipped {} duplicate frames", self.duplicate_frames);
+        }
+
+        Ok(bits)
+    }
+}
+
+/// Camera found by `list_cameras`
+#[derive(Debug, Clone)]
+pub struct CameraInfo {
+    /// Index to pass as `--camera-index`
+    pub index: i32,
+    /// Default frame width reported by the driver
+    pub width: u32,
+    /// Default frame height reported by the driver
+    pub height: u32,
+}
+
+/// Probe camera indices `0..max_index` and report the ones that open
+///
+/// # Arguments
+/// * `max_index` - Number of indices to try
+///
+/// # Returns
+/// Cameras that opened successfully
+pub fn list_cameras(max_index: i32) -> Vec<CameraInfo> {
+    (0..max_index)
+        .filter_map(|index| {
+            let cap = videoio::VideoCapture::new(index, videoio::CAP_ANY).ok()?;
+            if !cap.is_opened().unwrap_or(false) {
+                return None;
+            }
+            Some(CameraInfo {
+                index,
+                width: cap.get(videoio::CAP_PROP_FRAME_WIDTH).unwrap_or(0.0) as u32,
+                height: cap.get(videoio::CAP_PROP_FRAME_HEIGHT).unwrap_or(0.0) as u32,
+            })
+        })
+        .collect()
+}
+
+/// Generates random bits using quantum noise from webcam CMOS sensor
+///
+/// # Arguments
+/// * `num_bits` - Number of random bits to generate
+/// * `config` - Camera, resolution and bit-plane settings
+/// * `cancel` - Flag that stops collection early when set
+///
+/// # Returns
+/// Vector of random bits (0s and 1s as u8), shorter than `num_bits` if cancelled
+pub fn webcam_qrng(num_bits: usize, config: &WebcamConfig, cancel: &AtomicBool) -> Result<Vec<u8>> {
+    // The camera is released when the source goes out of scope
+    WebcamSource::with_config(config.clone()).collect_bits_cancellable(num_bits, cancel)
+}